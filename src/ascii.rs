@@ -0,0 +1,173 @@
+use crate::models::*;
+use crate::solve::State;
+use crate::utils::{free_cells, BitMatrix};
+
+use std::fmt::Write;
+
+const RESET: &str = "\x1b[0m";
+// foreground colors of the robots, cycled
+const ROBOT_STYLES: [&str; 6] = ["1;31", "1;34", "1;32", "1;35", "1;36", "1;33"];
+
+#[derive(Clone, PartialEq)]
+enum Cell {
+    Wall,
+    Obstacle,
+    Unpainted,
+    Painted,
+    Booster(BoosterType),
+    Hand(usize, bool),
+    Robot(usize, Direction),
+}
+
+impl Cell {
+    fn symbol(&self) -> char {
+        match self {
+            Cell::Wall => '#',
+            Cell::Obstacle => '%',
+            Cell::Unpainted => '.',
+            Cell::Painted => ' ',
+            Cell::Booster(kind) => kind.to_string().chars().next().unwrap(),
+            Cell::Hand(_, _) => '+',
+            Cell::Robot(_, Direction::Left) => '<',
+            Cell::Robot(_, Direction::Up) => '^',
+            Cell::Robot(_, Direction::Right) => '>',
+            Cell::Robot(_, Direction::Down) => 'v',
+        }
+    }
+
+    // SGR parameters, painted cells get a yellow background
+    fn style(&self) -> String {
+        match self {
+            Cell::Wall => "90".to_owned(),
+            Cell::Obstacle => "37".to_owned(),
+            Cell::Unpainted => "0".to_owned(),
+            Cell::Painted => "43".to_owned(),
+            Cell::Booster(kind) => match kind {
+                BoosterType::NewHand => "1;33",
+                BoosterType::FastMove => "1;35",
+                BoosterType::Drill => "1;32",
+                BoosterType::Teleports => "1;34",
+                BoosterType::Cloning => "1;36",
+                BoosterType::Spawn => "1;31",
+            }
+            .to_owned(),
+            Cell::Hand(idx, painted) => {
+                let style = ROBOT_STYLES[idx % ROBOT_STYLES.len()];
+                if *painted {
+                    format!("{};43", style)
+                } else {
+                    style.to_owned()
+                }
+            }
+            Cell::Robot(idx, _) => format!("{};7", ROBOT_STYLES[idx % ROBOT_STYLES.len()]),
+        }
+    }
+}
+
+// Cells inside the map polygon, obstacles included.
+fn map_cells(task: &Task) -> BitMatrix {
+    BitMatrix::from_polygons(task.width, task.height, std::iter::once(&task.map))
+}
+
+// Rows from the top, so that y grows upwards as in the task.
+fn draw(grid: &[Vec<Cell>], ansi: bool) -> String {
+    let mut out = String::new();
+    for row in grid.iter().rev() {
+        let mut style = None;
+        for cell in row {
+            if ansi && style.as_ref() != Some(cell) {
+                write!(out, "{}\x1b[{}m", RESET, cell.style()).unwrap();
+                style = Some(cell.clone());
+            }
+            out.push(cell.symbol());
+        }
+        if ansi {
+            out.push_str(RESET);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Walls, obstacles, boosters and the robot at its initial position.
+pub fn task_to_ascii(task: &Task, ansi: bool) -> String {
+    let inside = map_cells(task);
+    let free = free_cells(task);
+    let mut grid = (0..task.height)
+        .map(|y| {
+            (0..task.width)
+                .map(|x| {
+                    let p = Point::new(x as i32, y as i32);
+                    if free.get(p) == Some(&true) {
+                        Cell::Unpainted
+                    } else if inside.get(p) == Some(&true) {
+                        Cell::Obstacle
+                    } else {
+                        Cell::Wall
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for b in &task.boosters {
+        grid[b.point.y as usize][b.point.x as usize] = Cell::Booster(b.kind.clone());
+    }
+    let p = task.initial;
+    grid[p.y as usize][p.x as usize] = Cell::Robot(0, Direction::Right);
+    draw(&grid, ansi)
+}
+
+// The solver's view of the map with every robot and its manipulators.
+pub fn state_to_ascii(state: &State, ansi: bool) -> String {
+    let task = state.task();
+    let inside = map_cells(task);
+    let mut grid = (0..task.height)
+        .map(|y| {
+            (0..task.width)
+                .map(|x| {
+                    let p = Point::new(x as i32, y as i32);
+                    if let Some(kind) = state.booster_at(p) {
+                        Cell::Booster(kind.clone())
+                    } else if state.is_free(p) {
+                        if state.is_passed(p) {
+                            Cell::Painted
+                        } else {
+                            Cell::Unpainted
+                        }
+                    } else if inside.get(p) == Some(&true) {
+                        Cell::Obstacle
+                    } else {
+                        Cell::Wall
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for idx in 0..state.robot_count() {
+        for p in state.robot_bodies(idx) {
+            if let Some(cell) = grid
+                .get_mut(p.y as usize)
+                .and_then(|row| row.get_mut(p.x as usize))
+            {
+                if *cell == Cell::Unpainted || *cell == Cell::Painted {
+                    *cell = Cell::Hand(idx, *cell == Cell::Painted);
+                }
+            }
+        }
+    }
+    for idx in 0..state.robot_count() {
+        let place = state.robot_place(idx);
+        let p = place.point();
+        grid[p.y as usize][p.x as usize] = Cell::Robot(idx, place.dir());
+    }
+    let mut out = draw(&grid, ansi);
+    writeln!(
+        out,
+        "turn {}, {} cells left, {} robots",
+        state.turn(),
+        state.remaining(),
+        state.robot_count()
+    )
+    .unwrap();
+    out
+}
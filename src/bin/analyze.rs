@@ -0,0 +1,270 @@
+use clap::{App, Arg};
+use icfpc::layout::{choose_layout, corridor_width, corridor_widths};
+use icfpc::models::*;
+use icfpc::parse::read_inputs;
+use icfpc::utils::{free_cells, BitMatrix};
+use serde::Serialize;
+
+use std::collections::VecDeque;
+
+// corridor widths are counted in these inclusive ranges, None for no bound
+const WIDTH_BUCKETS: [(usize, Option<usize>); 7] = [
+    (1, Some(1)),
+    (2, Some(2)),
+    (3, Some(3)),
+    (4, Some(5)),
+    (6, Some(9)),
+    (10, Some(19)),
+    (20, None),
+];
+
+#[derive(Serialize, Default)]
+struct BoosterCounts {
+    new_hand: usize,
+    fast_move: usize,
+    drill: usize,
+    teleports: usize,
+    cloning: usize,
+    spawn: usize,
+}
+
+#[derive(Serialize)]
+struct WidthBucket {
+    min: usize,
+    max: Option<usize>,
+    cells: usize,
+}
+
+impl WidthBucket {
+    fn label(&self) -> String {
+        match self.max {
+            Some(max) if max == self.min => format!("{}", max),
+            Some(max) => format!("{}_{}", self.min, max),
+            None => format!("{}_plus", self.min),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Analysis {
+    id: String,
+    width: usize,
+    height: usize,
+    // cells to be wrapped
+    area: usize,
+    obstacles: usize,
+    components: usize,
+    // free cells with a single free neighbour
+    dead_ends: usize,
+    median_corridor_width: usize,
+    layout: String,
+    corridor_widths: Vec<WidthBucket>,
+    boosters: BoosterCounts,
+    // clones can only be made with a spawn point on the map
+    spawn_available: bool,
+}
+
+fn neighbours(p: Point) -> [Point; 4] {
+    [
+        Point::new(p.x - 1, p.y),
+        Point::new(p.x + 1, p.y),
+        Point::new(p.x, p.y - 1),
+        Point::new(p.x, p.y + 1),
+    ]
+}
+
+fn count_components(free: &BitMatrix) -> usize {
+    let mut seen = BitMatrix::new(free.width(), free.height(), false);
+    let mut queue = VecDeque::new();
+    let mut components = 0;
+    for start in free.iter_ones() {
+        if seen.get(start) == Some(&true) {
+            continue;
+        }
+        components += 1;
+        seen.set(start, true);
+        queue.push_back(start);
+        while let Some(p) = queue.pop_front() {
+            for q in neighbours(p).iter() {
+                if free.get(*q) == Some(&true) && seen.try_set(*q, true) == Some(false) {
+                    queue.push_back(*q);
+                }
+            }
+        }
+    }
+    components
+}
+
+fn analyze(task: &Task) -> Analysis {
+    let free = free_cells(task);
+    let dead_ends = free
+        .iter_ones()
+        .filter(|&p| {
+            neighbours(p)
+                .iter()
+                .filter(|&&q| free.get(q) == Some(&true))
+                .count()
+                == 1
+        })
+        .count();
+
+    let widths = corridor_widths(task)
+        .into_iter()
+        .filter(|&w| w > 0)
+        .collect::<Vec<_>>();
+    let corridor_widths = WIDTH_BUCKETS
+        .iter()
+        .map(|&(min, max)| WidthBucket {
+            min,
+            max,
+            cells: widths
+                .iter()
+                .filter(|&&w| w >= min && max.is_none_or(|max| w <= max))
+                .count(),
+        })
+        .collect();
+
+    let mut boosters = BoosterCounts::default();
+    for b in &task.boosters {
+        let count = match b.kind {
+            BoosterType::NewHand => &mut boosters.new_hand,
+            BoosterType::FastMove => &mut boosters.fast_move,
+            BoosterType::Drill => &mut boosters.drill,
+            BoosterType::Teleports => &mut boosters.teleports,
+            BoosterType::Cloning => &mut boosters.cloning,
+            BoosterType::Spawn => &mut boosters.spawn,
+        };
+        *count += 1;
+    }
+
+    let (width, height) = task.map.size();
+    Analysis {
+        id: task.id.clone(),
+        width,
+        height,
+        area: free.count_ones(),
+        obstacles: task.obstacles.len(),
+        components: count_components(&free),
+        dead_ends,
+        median_corridor_width: corridor_width(task),
+        layout: choose_layout(task).to_string(),
+        corridor_widths,
+        spawn_available: boosters.spawn > 0,
+        boosters,
+    }
+}
+
+// Quotes a field containing separators or quotes as in RFC 4180.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn print_csv(analyses: &[Analysis]) {
+    let mut header = vec![
+        "id",
+        "width",
+        "height",
+        "area",
+        "obstacles",
+        "components",
+        "dead_ends",
+        "median_corridor_width",
+        "layout",
+    ]
+    .into_iter()
+    .map(|s| s.to_owned())
+    .collect::<Vec<_>>();
+    if let Some(first) = analyses.first() {
+        for bucket in &first.corridor_widths {
+            header.push(format!("corridor_{}", bucket.label()));
+        }
+    }
+    for kind in &[
+        "new_hand",
+        "fast_move",
+        "drill",
+        "teleports",
+        "cloning",
+        "spawn",
+    ] {
+        header.push(format!("boosters_{}", kind));
+    }
+    header.push("spawn_available".to_owned());
+    println!("{}", header.join(","));
+
+    for a in analyses {
+        let mut row = vec![
+            a.id.clone(),
+            a.width.to_string(),
+            a.height.to_string(),
+            a.area.to_string(),
+            a.obstacles.to_string(),
+            a.components.to_string(),
+            a.dead_ends.to_string(),
+            a.median_corridor_width.to_string(),
+            a.layout.clone(),
+        ];
+        row.extend(a.corridor_widths.iter().map(|b| b.cells.to_string()));
+        let b = &a.boosters;
+        row.extend(
+            [
+                b.new_hand,
+                b.fast_move,
+                b.drill,
+                b.teleports,
+                b.cloning,
+                b.spawn,
+            ]
+            .iter()
+            .map(|c| c.to_string()),
+        );
+        row.push(a.spawn_available.to_string());
+        let row = row.iter().map(|f| csv_field(f)).collect::<Vec<_>>();
+        println!("{}", row.join(","));
+    }
+}
+
+// Describes the tasks to help choose strategies and boosters to buy.
+fn main() {
+    let matches = App::new("Task analyzer")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .takes_value(true)
+                .multiple(true)
+                .help("task files or directories of them"),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .long("recursive")
+                .help("search input directories recursively"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["csv", "json"])
+                .help("output format (csv by default)"),
+        )
+        .get_matches();
+    let input_paths = matches
+        .values_of("input")
+        .expect("no input specified")
+        .collect::<Vec<_>>();
+    let recursive = matches.is_present("recursive");
+
+    let inputs = read_inputs(&input_paths, recursive);
+    let analyses = inputs
+        .iter()
+        .map(|input| analyze(&input.task))
+        .collect::<Vec<_>>();
+    match matches.value_of("format").unwrap_or("csv") {
+        "json" => println!("{}", serde_json::to_string_pretty(&analyses).unwrap()),
+        _ => print_csv(&analyses),
+    }
+}
@@ -0,0 +1,77 @@
+use clap::{App, Arg};
+use icfpc::parse::read_inputs;
+use icfpc::solve::{determine_buy, solve_small};
+use icfpc::strategy::{find_strategy, strategies, DEFAULT_STRATEGY};
+
+use std::time::{Duration, Instant};
+
+// Times a single solver run per task with a fixed seed.
+fn main() {
+    let matches = App::new("Solver benchmark")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .takes_value(true)
+                .multiple(true)
+                .help("task files or directories of them"),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .long("recursive")
+                .help("search input directories recursively"),
+        )
+        .arg(
+            Arg::with_name("strategy")
+                .long("strategy")
+                .takes_value(true)
+                .help("solver strategy (greedy by default)"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .help("seed of every run"),
+        )
+        .get_matches();
+    let input_paths = matches
+        .values_of("input")
+        .expect("no input specified")
+        .collect::<Vec<_>>();
+    let recursive = matches.is_present("recursive");
+    let seed = matches
+        .value_of("seed")
+        .unwrap_or("0")
+        .parse::<u64>()
+        .unwrap();
+    let strategy_name = matches.value_of("strategy").unwrap_or(DEFAULT_STRATEGY);
+    let strategy = find_strategy(strategy_name).unwrap_or_else(|| {
+        let names = strategies().iter().map(|s| s.name()).collect::<Vec<_>>();
+        panic!(
+            "unknown strategy {} (available: {})",
+            strategy_name,
+            names.join(", ")
+        )
+    });
+
+    let inputs = read_inputs(&input_paths, recursive);
+
+    let mut sum_turns = 0;
+    let mut sum_elapsed = Duration::from_secs(0);
+    for input in inputs {
+        let buy = determine_buy(&input.task);
+        let now = Instant::now();
+        let commands = solve_small(input.task, &buy, seed, strategy);
+        let elapsed = now.elapsed();
+        println!(
+            "{}: {} turns in {}ms",
+            input.id,
+            commands.len(),
+            elapsed.as_millis()
+        );
+        sum_turns += commands.len();
+        sum_elapsed += elapsed;
+    }
+    println!("turns: {}", sum_turns);
+    println!("elapsed: {}ms", sum_elapsed.as_millis());
+}
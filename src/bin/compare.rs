@@ -1,5 +1,5 @@
 use clap::{App, Arg};
-use icfpc::parse::read_all_inputs;
+use icfpc::parse::read_inputs;
 use icfpc::parse::read_commands;
 use std::collections::HashMap;
 
@@ -13,7 +13,13 @@ fn main() {
             Arg::with_name("input")
                 .long("input")
                 .takes_value(true)
-                .help("input root directory"),
+                .multiple(true)
+                .help("task files or directories of them"),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .long("recursive")
+                .help("search input directories recursively"),
         )
         .arg(
             Arg::with_name("file")
@@ -29,8 +35,12 @@ fn main() {
         )
         .get_matches();
 
-    let input_root = matches.value_of("input").expect("no input specified");
-    let inputs = read_all_inputs(input_root);
+    let input_paths = matches
+        .values_of("input")
+        .expect("no input specified")
+        .collect::<Vec<_>>();
+    let recursive = matches.is_present("recursive");
+    let inputs = read_inputs(&input_paths, recursive);
     let output_root = matches.value_of("output").expect("no output specified");
 
     let path_file = matches.value_of("file").expect("no file specified");
@@ -42,20 +52,31 @@ fn main() {
                 let mut output_str = String::new();
                 output_file.read_to_string(&mut output_str).unwrap();
                 if !output_str.is_empty() {
-                    let commands = read_commands(&output_str);
-                    result
-                        .entry(input.id.to_owned())
-                        .or_default()
-                        .push((commands.len(), output_root.to_owned()));
+                    match read_commands(&output_str) {
+                        Ok(commands) => result
+                            .entry(input.id.to_owned())
+                            .or_default()
+                            .push((commands.len(), output_root.to_owned())),
+                        Err(e) => eprintln!("{}: {}", output_path, e),
+                    }
                 }
             }
         }
     }
     for input in &inputs {
-        let v = result.get_mut(&input.id).unwrap();
+        let v = match result.get_mut(&input.id) {
+            Some(v) => v,
+            None => {
+                eprintln!("{}: no valid solution", input.id);
+                continue;
+            }
+        };
         v.sort();
         let best_root = &v[0].1;
-        println!("{}: {} ({} {})", input.id, best_root, v[0].0, v[1].0);
+        match v.get(1) {
+            Some(second) => println!("{}: {} ({} {})", input.id, best_root, v[0].0, second.0),
+            None => println!("{}: {} ({})", input.id, best_root, v[0].0),
+        }
         let best_path = format!("{}/{}", best_root, input.output_file_name());
         let new_path = format!("{}/{}", output_root, input.output_file_name());
         std::fs::copy(best_path, new_path).unwrap();
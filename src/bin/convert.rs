@@ -0,0 +1,182 @@
+use clap::{App, Arg};
+use icfpc::models::*;
+use icfpc::parse::{read_buy, read_commands, read_puzzle, read_task};
+use icfpc::validate::validate_task;
+use serde::Serialize;
+
+use std::io::Read;
+use std::path::Path;
+use std::process;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Kind {
+    Task,
+    Solution,
+    Buy,
+    Puzzle,
+}
+
+impl Kind {
+    fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "desc" | "task" => Some(Kind::Task),
+            "sol" | "solution" => Some(Kind::Solution),
+            "buy" => Some(Kind::Buy),
+            "cond" | "puzzle" => Some(Kind::Puzzle),
+            _ => None,
+        }
+    }
+
+    // prob-001.desc and prob-001.desc.json are both tasks
+    fn from_path(path: &Path) -> Option<Kind> {
+        let path = if path.extension().is_some_and(|e| e == "json") {
+            Path::new(path.file_stem()?)
+        } else {
+            path
+        };
+        Kind::from_name(path.extension()?.to_str()?)
+    }
+}
+
+fn to_json<T: Serialize>(value: &T, pretty: bool) -> String {
+    if pretty {
+        serde_json::to_string_pretty(value).unwrap()
+    } else {
+        serde_json::to_string(value).unwrap()
+    }
+}
+
+fn text_to_json(kind: Kind, s: &str, id: String, pretty: bool) -> Result<String, String> {
+    let s = s.trim_end();
+    let json = match kind {
+        Kind::Task => to_json(&read_task(s, id).map_err(|e| e.to_string())?, pretty),
+        Kind::Solution => to_json(&read_commands(s).map_err(|e| e.to_string())?, pretty),
+        Kind::Buy => to_json(&read_buy(s).map_err(|e| e.to_string())?, pretty),
+        Kind::Puzzle => to_json(&read_puzzle(s).map_err(|e| e.to_string())?, pretty),
+    };
+    Ok(json)
+}
+
+fn json_to_text(kind: Kind, s: &str) -> Result<String, String> {
+    let text = match kind {
+        Kind::Task => {
+            let mut task: Task = serde_json::from_str(s).map_err(|e| e.to_string())?;
+            // validated first since the size derived from an empty map panics
+            validate_task(&task).map_err(|e| e.to_string())?;
+            task.width = task.map.compute_width();
+            task.height = task.map.compute_height();
+            task.to_string()
+        }
+        Kind::Solution => {
+            let commands: Commands = serde_json::from_str(s).map_err(|e| e.to_string())?;
+            if commands.robot_count() == 0 {
+                return Err("a solution needs commands for at least one robot".to_owned());
+            }
+            commands.to_string()
+        }
+        Kind::Buy => {
+            let buy: Buy = serde_json::from_str(s).map_err(|e| e.to_string())?;
+            buy.to_string()
+        }
+        Kind::Puzzle => {
+            let puzzle: Puzzle = serde_json::from_str(s).map_err(|e| e.to_string())?;
+            puzzle.to_string()
+        }
+    };
+    Ok(text)
+}
+
+// Converts between the contest text formats and JSON.
+fn main() {
+    let matches = App::new("Format converter")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("input")
+                .required(true)
+                .help("file to convert, or - for stdin"),
+        )
+        .arg(
+            Arg::with_name("kind")
+                .long("kind")
+                .takes_value(true)
+                .possible_values(&["task", "solution", "buy", "puzzle"])
+                .help("format of the input (guessed from its extension if omitted)"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .possible_values(&["json", "text"])
+                .help("output format (json unless the input ends with .json)"),
+        )
+        .arg(
+            Arg::with_name("pretty")
+                .long("pretty")
+                .help("indent JSON output"),
+        )
+        .get_matches();
+
+    let input = matches.value_of("input").unwrap();
+    let path = Path::new(input);
+    let kind = match matches.value_of("kind") {
+        Some(kind) => Kind::from_name(kind),
+        None => Kind::from_path(path),
+    };
+    let kind = kind.unwrap_or_else(|| {
+        eprintln!("{}: cannot tell the format, use --kind", input);
+        process::exit(1);
+    });
+    let is_json = path.extension().is_some_and(|e| e == "json");
+    let to_text = match matches.value_of("to") {
+        Some(to) => to == "text",
+        None => is_json,
+    };
+
+    let content = if input == "-" {
+        let mut s = String::new();
+        std::io::stdin().read_to_string(&mut s).map(|_| s)
+    } else {
+        std::fs::read_to_string(path)
+    };
+    let content = content.unwrap_or_else(|e| {
+        eprintln!("{}: {}", input, e);
+        process::exit(1);
+    });
+
+    let result = if to_text {
+        json_to_text(kind, &content)
+    } else {
+        let id = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .trim_start_matches("prob-")
+            .to_owned();
+        text_to_json(kind, &content, id, matches.is_present("pretty"))
+    };
+    match result {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("{}: {}", input, e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_round_trip() {
+        let text = "(0,0),(6,0),(6,1),(8,1),(8,2),(6,2),(6,3),(0,3)#(0,0)##B(1,1)";
+        let json = text_to_json(Kind::Task, text, "001".to_owned(), false).unwrap();
+        assert_eq!(json_to_text(Kind::Task, &json).unwrap(), text);
+    }
+
+    #[test]
+    fn task_with_empty_map() {
+        let json = r#"{"id":"1","width":0,"height":0,"map":[],"initial":{"x":0,"y":0},"obstacles":[],"boosters":[]}"#;
+        assert!(json_to_text(Kind::Task, json).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use clap::{App, Arg};
+use icfpc::ascii::task_to_ascii;
+use icfpc::models::*;
+use icfpc::parse::{read_buy, read_commands, read_inputs, Input};
+use icfpc::render::{render_svg, Replay};
+
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+
+// Reads the solution of an input from a directory laid out like the solver output.
+fn read_replay(input: &Input, dir: &str) -> Result<Option<Replay>, String> {
+    let sol_path = Path::new(dir).join(input.output_file_name());
+    let sol_str = match fs::read_to_string(&sol_path) {
+        Ok(s) => s,
+        Err(_) => return Ok(None),
+    };
+    let commands = read_commands(&sol_str).map_err(|e| format!("{}: {}", sol_path.display(), e))?;
+    let buy_path = Path::new(dir).join(input.buy_file_name());
+    let buy = match fs::read_to_string(&buy_path) {
+        Ok(s) => read_buy(&s).map_err(|e| format!("{}: {}", buy_path.display(), e))?,
+        Err(_) => Buy::new(),
+    };
+    Ok(Some(Replay::new(&input.task, &buy, &commands)))
+}
+
+// Draws tasks, and their solutions if there are any, as SVG images.
+fn main() {
+    let matches = App::new("Task renderer")
+        .version("0.1.0")
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .takes_value(true)
+                .multiple(true)
+                .help("task files or directories of them"),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .long("recursive")
+                .help("search input directories recursively"),
+        )
+        .arg(
+            Arg::with_name("id")
+                .long("id")
+                .takes_value(true)
+                .multiple(true)
+                .help("problem IDs to render (all inputs if omitted)"),
+        )
+        .arg(
+            Arg::with_name("solution")
+                .long("solution")
+                .takes_value(true)
+                .help("directory of .sol and .buy files to replay"),
+        )
+        .arg(
+            Arg::with_name("ascii")
+                .long("ascii")
+                .help("print the tasks as text instead of writing SVG files"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .help("directory to write the SVG files to"),
+        )
+        .get_matches();
+    let input_paths = matches
+        .values_of("input")
+        .expect("no input specified")
+        .collect::<Vec<_>>();
+    let recursive = matches.is_present("recursive");
+    let ids = matches.values_of("id").map(|ids| ids.collect::<Vec<_>>());
+    let solution_root = matches.value_of("solution");
+    let output_root = matches.value_of("output").unwrap_or(".");
+
+    let inputs = read_inputs(&input_paths, recursive)
+        .into_iter()
+        .filter(|input| {
+            ids.as_ref()
+                .is_none_or(|ids| ids.contains(&input.id.as_str()))
+        })
+        .collect::<Vec<_>>();
+    if inputs.is_empty() {
+        eprintln!("no matching input");
+        std::process::exit(1);
+    }
+
+    if matches.is_present("ascii") {
+        let ansi = std::io::stdout().is_terminal();
+        for input in inputs {
+            println!("{}:", input.id);
+            print!("{}", task_to_ascii(&input.task, ansi));
+        }
+        return;
+    }
+
+    fs::create_dir_all(output_root).unwrap();
+    for input in inputs {
+        let replay = match solution_root.map(|dir| read_replay(&input, dir)) {
+            Some(Ok(replay)) => replay,
+            Some(Err(e)) => {
+                eprintln!("{}: INVALID {}", input.id, e);
+                None
+            }
+            None => None,
+        };
+        let svg = render_svg(&input.task, replay.as_ref());
+        let output_path = Path::new(output_root).join(format!("{}.svg", input.name));
+        fs::write(&output_path, svg).unwrap();
+        match replay {
+            Some(Replay { error: Some(e), .. }) => {
+                println!("{}: {} (invalid: {})", input.id, output_path.display(), e)
+            }
+            Some(replay) => println!(
+                "{}: {} ({} turns)",
+                input.id,
+                output_path.display(),
+                replay.turns
+            ),
+            None => println!("{}: {}", input.id, output_path.display()),
+        }
+    }
+}
@@ -1,14 +1,14 @@
 use clap::{App, Arg};
 use icfpc::models::*;
-use icfpc::parse::read_all_inputs;
+use icfpc::parse::read_inputs;
 
 use icfpc::parse::read_buy;
 use icfpc::parse::read_commands;
-use icfpc::utils::Matrix;
+use icfpc::sim::{SimError, Simulator};
 use std::collections::HashMap;
 
-use std::fs::File;
-use std::io::Read;
+use std::fs;
+use std::io::ErrorKind;
 
 
 struct ScoreInfo {
@@ -46,37 +46,18 @@ impl ScoreInfo {
     }
 }
 
-fn score_small(task: Task, commands: Commands) -> ScoreInfo {
-    let map_points = task.map.enumerate_points();
+fn score_small(task: &Task, buy: &Buy, commands: &Commands) -> Result<ScoreInfo, SimError> {
+    let sim = Simulator::new(task, buy);
+    let area = sim.area();
+    let team_time = sim.run(commands)?;
 
-    let width = map_points.iter().map(|p| p.x).max().unwrap() + 1;
-    let height = map_points.iter().map(|p| p.y).max().unwrap() + 1;
-    let mut remaining = 0;
-    let mut passed = Matrix::new(width as usize, height as usize, true);
-    let mut valid = Matrix::new(width as usize, height as usize, false);
-
-    for &p in &map_points {
-        passed.set(p, false);
-        valid.set(p, true);
-        remaining += 1;
-    }
-
-    for o in &task.obstacles {
-        for &p in o.enumerate_points().iter() {
-            if let Some(true) = valid.get(p) {
-                valid.set(p, false);
-                passed.set(p, true);
-                remaining -= 1;
-            }
-        }
-    }
-
-    ScoreInfo {
-        width: width as usize,
-        height: height as usize,
-        best_estimated: remaining * 20 / 100,
-        team_time: commands.len(),
-    }
+    let (width, height) = task.map.size();
+    Ok(ScoreInfo {
+        width,
+        height,
+        best_estimated: area * 20 / 100,
+        team_time,
+    })
 }
 
 fn main() {
@@ -86,7 +67,13 @@ fn main() {
             Arg::with_name("input")
                 .long("input")
                 .takes_value(true)
-                .help("input root directory"),
+                .multiple(true)
+                .help("task files or directories of them"),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .long("recursive")
+                .help("search input directories recursively"),
         )
         .arg(
             Arg::with_name("output")
@@ -95,28 +82,59 @@ fn main() {
                 .help("output directory"),
         )
         .get_matches();
-    let input_root = matches.value_of("input").expect("no input specified");
+    let input_paths = matches
+        .values_of("input")
+        .expect("no input specified")
+        .collect::<Vec<_>>();
+    let recursive = matches.is_present("recursive");
     let output_root = matches.value_of("output").expect("no output specified");
-    let inputs = read_all_inputs(input_root);
+    let inputs = read_inputs(&input_paths, recursive);
 
     let mut sum_buy = 0;
     let mut sum_scores = 0.0;
+    let mut invalids = Vec::new();
+    let mut missings = Vec::new();
     for input in inputs {
         let commands = {
             let output_path = format!("{}/{}", output_root, input.output_file_name());
-            let mut output_file = File::open(&output_path).unwrap();
-            let mut output_str = String::new();
-            output_file.read_to_string(&mut output_str).unwrap();
-            read_commands(&output_str)
+            let output_str = match fs::read_to_string(&output_path) {
+                Ok(s) => s,
+                Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                    eprintln!("{}: MISSING {}", input.id, output_path);
+                    missings.push(input.id.clone());
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("{}: INVALID {}: {}", input.id, output_path, e);
+                    invalids.push(input.id.clone());
+                    continue;
+                }
+            };
+            match read_commands(&output_str) {
+                Ok(commands) => commands,
+                Err(e) => {
+                    eprintln!("{}: INVALID {}: {}", input.id, output_path, e);
+                    invalids.push(input.id.clone());
+                    continue;
+                }
+            }
         };
+        // no buy file means nothing was bought
         let buy = {
             let output_path = format!("{}/{}", output_root, input.buy_file_name());
-            if let Ok(mut output_file) = File::open(&output_path) {
-                let mut output_str = String::new();
-                output_file.read_to_string(&mut output_str).unwrap();
-                read_buy(&output_str)
-            } else {
-                Buy::new()
+            match fs::read_to_string(&output_path).map(|s| read_buy(&s)) {
+                Ok(Ok(buy)) => buy,
+                Ok(Err(e)) => {
+                    eprintln!("{}: INVALID {}: {}", input.id, output_path, e);
+                    invalids.push(input.id.clone());
+                    continue;
+                }
+                Err(ref e) if e.kind() == ErrorKind::NotFound => Buy::new(),
+                Err(e) => {
+                    eprintln!("{}: INVALID {}: {}", input.id, output_path, e);
+                    invalids.push(input.id.clone());
+                    continue;
+                }
             }
         };
         let mut counter = HashMap::new();
@@ -132,18 +150,61 @@ fn main() {
             counter.get(&BoosterType::Teleports).unwrap_or(&0),
             counter.get(&BoosterType::Cloning).unwrap_or(&0),
         );
-        let score_info = score_small(input.task, commands);
-        eprintln!(
-            "{}: {} (buy: {}) ({})",
-            input.id,
-            score_info.debug(),
-            buy.money(),
-            count_info
-        );
-        sum_scores += score_info.score();
+        match score_small(&input.task, &buy, &commands) {
+            Ok(score_info) => {
+                eprintln!(
+                    "{}: {} (buy: {}) ({})",
+                    input.id,
+                    score_info.debug(),
+                    buy.money(),
+                    count_info
+                );
+                sum_scores += score_info.score();
+            }
+            Err(e) => {
+                eprintln!(
+                    "{}: INVALID {} (buy: {}) ({})",
+                    input.id,
+                    e,
+                    buy.money(),
+                    count_info
+                );
+                invalids.push(input.id.clone());
+            }
+        }
         sum_buy += buy.money();
     }
     println!("output: {}", output_root);
     println!("total_score: {}", sum_scores);
     println!("total_buy: {}", sum_buy);
+    if !invalids.is_empty() {
+        invalids.sort();
+        println!("invalid: {} ({})", invalids.len(), invalids.join(" "));
+    }
+    if !missings.is_empty() {
+        missings.sort();
+        println!("missing: {} ({})", missings.len(), missings.join(" "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use icfpc::parse::read_task;
+    use icfpc::strategy::default_strategy;
+    use icfpc::solve::solve_small;
+
+    #[test]
+    fn score_of_a_square() {
+        let task = read_task("(0,0),(4,0),(4,4),(0,4)#(0,0)##", "test".to_owned()).unwrap();
+        let commands = solve_small(task.clone(), &Buy::new(), 0, default_strategy());
+        let info = score_small(&task, &Buy::new(), &commands).unwrap();
+        assert_eq!((info.width, info.height), (4, 4));
+        assert_eq!(info.log_wh(), 4.0);
+        // a fifth of the 16 cells, rounded down
+        assert_eq!(info.best_estimated, 3);
+        assert_eq!(info.team_time, commands.len());
+        let expected = 1000.0 * 4.0 * 3.0 / commands.len() as f64;
+        assert!((info.score() - expected).abs() < 1e-9);
+    }
 }
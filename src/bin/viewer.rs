@@ -0,0 +1,68 @@
+use clap::{App, Arg};
+use icfpc::models::*;
+use icfpc::parse::{read_buy, read_commands, read_input};
+use icfpc::render::Replay;
+use icfpc::viewer::render_html;
+
+use std::path::Path;
+use std::process;
+
+fn read_file(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(1);
+    })
+}
+
+// Writes an HTML page replaying a solution turn by turn.
+fn main() {
+    let matches = App::new("Replay viewer")
+        .version("0.1.0")
+        .arg(Arg::with_name("task").required(true).help("task file"))
+        .arg(
+            Arg::with_name("solution")
+                .required(true)
+                .help("solution file"),
+        )
+        .arg(
+            Arg::with_name("buy")
+                .long("buy")
+                .takes_value(true)
+                .help("boosters bought for the solution"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .takes_value(true)
+                .help("HTML file to write (stdout if omitted)"),
+        )
+        .get_matches();
+
+    // read_input reports why a task could not be read or is invalid
+    let task = match read_input(Path::new(matches.value_of("task").unwrap())) {
+        Some(input) => input.task,
+        None => process::exit(1),
+    };
+    let solution_path = matches.value_of("solution").unwrap();
+    let commands = read_commands(&read_file(solution_path)).unwrap_or_else(|e| {
+        eprintln!("{}: {}", solution_path, e);
+        process::exit(1);
+    });
+    let buy = match matches.value_of("buy") {
+        Some(buy_path) => read_buy(&read_file(buy_path)).unwrap_or_else(|e| {
+            eprintln!("{}: {}", buy_path, e);
+            process::exit(1);
+        }),
+        None => Buy::new(),
+    };
+
+    let replay = Replay::new(&task, &buy, &commands);
+    if let Some(e) = &replay.error {
+        eprintln!("{}: {}", solution_path, e);
+    }
+    let html = render_html(&task, &replay);
+    match matches.value_of("output") {
+        Some(output_path) => std::fs::write(output_path, html).unwrap(),
+        None => print!("{}", html),
+    }
+}
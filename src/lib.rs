@@ -1,9 +1,16 @@
 #[macro_use]
 extern crate jsonrpc_client_core;
 
+pub mod ascii;
+pub mod layout;
 pub mod mine;
 pub mod models;
 pub mod parse;
 pub mod solve;
+pub mod strategy;
 pub mod utils;
+pub mod validate;
 pub mod puzzle;
+pub mod render;
+pub mod viewer;
+pub mod sim;
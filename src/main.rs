@@ -1,20 +1,91 @@
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches, SubCommand};
 use indicatif::ProgressBar;
 use rayon::prelude::*;
 use std::fs::File;
-use std::io::Write;
+use std::io::{IsTerminal, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
 
+use icfpc::ascii::state_to_ascii;
 use icfpc::models::*;
-use icfpc::parse::read_all_inputs;
-use icfpc::solve::solve_small_while;
+use icfpc::parse::{read_inputs, read_task};
+use icfpc::sim::simulate;
+use icfpc::solve::{solve_small_while, SolveStats, Solver, State};
 use icfpc::solve::determine_buy;
+use icfpc::strategy::{find_strategy, strategies, Strategy, DEFAULT_STRATEGY};
+use icfpc::validate::validate_task;
 use std::time::Duration;
 
-fn solve<W: Write>(task: Task, f: &mut W, b: &mut W, duration: Duration) {
+// exit codes of the solve subcommand
+const EXIT_PARSE_FAILURE: i32 = 2;
+const EXIT_SOLVE_FAILURE: i32 = 3;
+
+// larger maps do not fit in a terminal
+const WATCH_MAX_SIZE: usize = 100;
+// pause after each redraw so that it can be followed
+const WATCH_DELAY: Duration = Duration::from_millis(50);
+
+fn solve<W: Write>(
+    task: Task,
+    f: &mut W,
+    b: &mut W,
+    s: &mut W,
+    duration: Duration,
+    seed: u64,
+    strategy: &dyn Strategy,
+) -> SolveStats {
     let buy = determine_buy(&task);
-    let cmds = solve_small_while(task, &buy, duration);
+    let (cmds, stats) = solve_small_while(task, &buy, duration, seed, strategy);
     write!(f, "{}", cmds).unwrap();
     write!(b, "{}", buy).unwrap();
+    writeln!(s, "{}", stats.seed).unwrap();
+    stats
+}
+
+// options shared by the batch mode and the solve subcommand
+fn solver_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("duration")
+            .long("duration")
+            .takes_value(true)
+            .help("millis to wait"),
+        Arg::with_name("strategy")
+            .long("strategy")
+            .takes_value(true)
+            .help("solver strategy (greedy by default)"),
+        Arg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .help("base seed of the solver (random if omitted)"),
+    ]
+}
+
+fn duration_of(matches: &ArgMatches) -> Duration {
+    let millis = matches
+        .value_of("duration")
+        .unwrap_or("300")
+        .parse::<u64>()
+        .unwrap();
+    Duration::from_millis(millis)
+}
+
+fn seed_of(matches: &ArgMatches) -> u64 {
+    matches
+        .value_of("seed")
+        .map(|s| s.parse::<u64>().unwrap())
+        .unwrap_or_else(rand::random)
+}
+
+fn strategy_of(matches: &ArgMatches) -> &'static dyn Strategy {
+    let strategy_name = matches.value_of("strategy").unwrap_or(DEFAULT_STRATEGY);
+    find_strategy(strategy_name).unwrap_or_else(|| {
+        let names = strategies().iter().map(|s| s.name()).collect::<Vec<_>>();
+        panic!(
+            "unknown strategy {} (available: {})",
+            strategy_name,
+            names.join(", ")
+        )
+    })
 }
 
 fn main() {
@@ -24,7 +95,13 @@ fn main() {
             Arg::with_name("input")
                 .long("input")
                 .takes_value(true)
-                .help("input root directory"),
+                .multiple(true)
+                .help("task files or directories of them"),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .long("recursive")
+                .help("search input directories recursively"),
         )
         .arg(
             Arg::with_name("output")
@@ -32,37 +109,172 @@ fn main() {
                 .takes_value(true)
                 .help("output directory"),
         )
-        .arg(
-            Arg::with_name("duration")
-                .long("duration")
-                .takes_value(true)
-                .help("millis to wait"),
+        .args(&solver_args())
+        .subcommand(
+            SubCommand::with_name("solve")
+                .about("solves a single task and prints the solution")
+                .after_help(
+                    "Exits with 2 if the task cannot be parsed or is invalid and 3 if solving it fails.",
+                )
+                .arg(
+                    Arg::with_name("task")
+                        .required(true)
+                        .help("task file, or - to read it from stdin"),
+                )
+                .arg(
+                    Arg::with_name("buy")
+                        .long("buy")
+                        .help("print the boosters to buy on a second line"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .takes_value(true)
+                        .value_name("N")
+                        .help("draw the map on stderr every N turns of the first run"),
+                )
+                .args(&solver_args()),
         )
         .get_matches();
 
-    let input_root = matches.value_of("input").expect("no input specified");
+    match matches.subcommand() {
+        ("solve", Some(matches)) => solve_single(matches),
+        _ => solve_all(&matches),
+    }
+}
+
+fn solve_single(matches: &ArgMatches) {
+    let task_path = matches.value_of("task").unwrap();
+    let task_str = if task_path == "-" {
+        let mut s = String::new();
+        std::io::stdin().read_to_string(&mut s).map(|_| s)
+    } else {
+        std::fs::read_to_string(task_path)
+    };
+    let task_str = task_str.unwrap_or_else(|e| {
+        eprintln!("{}: {}", task_path, e);
+        process::exit(EXIT_PARSE_FAILURE);
+    });
+    let task = read_task(task_str.trim_end(), String::new()).unwrap_or_else(|e| {
+        eprintln!("{}: {}", task_path, e);
+        process::exit(EXIT_PARSE_FAILURE);
+    });
+    if let Err(e) = validate_task(&task) {
+        eprintln!("{}: {}", task_path, e);
+        process::exit(EXIT_PARSE_FAILURE);
+    }
+
+    let duration = duration_of(matches);
+    let seed = seed_of(matches);
+    let strategy = strategy_of(matches);
+    let buy = determine_buy(&task);
+    let watch = matches
+        .value_of("watch")
+        .map(|n| n.parse::<usize>().unwrap())
+        .filter(|&n| {
+            let small = task.width <= WATCH_MAX_SIZE && task.height <= WATCH_MAX_SIZE;
+            if !small {
+                eprintln!(
+                    "not watching a {}x{} map (at most {}x{})",
+                    task.width, task.height, WATCH_MAX_SIZE, WATCH_MAX_SIZE
+                );
+            }
+            n > 0 && small
+        });
+    let ansi = std::io::stderr().is_terminal();
+    let watcher = |state: &State| {
+        if ansi {
+            // move to the top left and clear the screen
+            eprint!("\x1b[H\x1b[2J");
+        }
+        eprint!("{}", state_to_ascii(state, ansi));
+        std::thread::sleep(WATCH_DELAY);
+    };
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut solver = Solver::new(task.clone())
+            .buy(buy.clone())
+            .seed(seed)
+            .time_budget(duration)
+            .strategy(strategy);
+        if let Some(turns) = watch {
+            solver = solver.watch(turns, &watcher);
+        }
+        let solution = solver.solve();
+        (solution.commands, solution.stats)
+    }));
+    let (cmds, stats) = match result {
+        Ok(res) => res,
+        // the panic message has already been printed
+        Err(_) => process::exit(EXIT_SOLVE_FAILURE),
+    };
+    if let Err(e) = simulate(&task, &buy, &cmds) {
+        eprintln!("invalid solution (seed {}): {}", stats.seed, e);
+        process::exit(EXIT_SOLVE_FAILURE);
+    }
+
+    println!("{}", cmds);
+    if matches.is_present("buy") {
+        println!("{}", buy);
+    }
+    eprintln!(
+        "{} turns ({}, {} restarts, layout {}, seed {})",
+        stats.turns, stats.strategy, stats.restarts, stats.layout, stats.seed
+    );
+}
+
+fn solve_all(matches: &ArgMatches) {
+    let input_paths = matches
+        .values_of("input")
+        .expect("no input specified")
+        .collect::<Vec<_>>();
+    let recursive = matches.is_present("recursive");
     let output_root = matches.value_of("output");
-    let millis = matches
-        .value_of("duration")
-        .unwrap_or("300")
-        .parse::<u64>()
-        .unwrap();
-    let duration = Duration::from_millis(millis);
+    let duration = duration_of(matches);
+    let seed = seed_of(matches);
+    let strategy = strategy_of(matches);
 
-    let inputs = read_all_inputs(&input_root);
+    let inputs = read_inputs(&input_paths, recursive);
     let progress_bar = ProgressBar::new(inputs.len() as u64);
     inputs.into_par_iter().for_each(|input| {
-        let (mut output_file, mut buy_file): (Box<dyn Write>, Box<dyn Write>) = match output_root {
+        let (mut output_file, mut buy_file, mut seed_file): (
+            Box<dyn Write>,
+            Box<dyn Write>,
+            Box<dyn Write>,
+        ) = match output_root {
             Some(output_root) => {
                 let output_path = format!("{}/{}", output_root, input.output_file_name());
                 let output_file = File::create(&output_path).unwrap();
                 let buy_path = format!("{}/{}", output_root, input.buy_file_name());
                 let buy_file = File::create(&buy_path).unwrap();
-                (Box::new(output_file), Box::new(buy_file))
+                let seed_path = format!("{}/{}", output_root, input.seed_file_name());
+                let seed_file = File::create(&seed_path).unwrap();
+                (Box::new(output_file), Box::new(buy_file), Box::new(seed_file))
             }
-            None => (Box::new(std::io::stdout()), Box::new(std::io::sink())),
+            None => (
+                Box::new(std::io::stdout()),
+                Box::new(std::io::sink()),
+                Box::new(std::io::sink()),
+            ),
         };
-        solve(input.task, &mut output_file, &mut buy_file, duration);
+        let stats = solve(
+            input.task,
+            &mut output_file,
+            &mut buy_file,
+            &mut seed_file,
+            duration,
+            seed,
+            strategy,
+        );
+        progress_bar.println(format!(
+            "{}: {} turns ({}, {} restarts, {} aborted, layout {}, seed {})",
+            input.id,
+            stats.turns,
+            stats.strategy,
+            stats.restarts,
+            stats.aborted,
+            stats.layout,
+            stats.seed
+        ));
         progress_bar.inc(1);
     });
     progress_bar.finish();
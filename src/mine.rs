@@ -8,7 +8,9 @@ use serde_json::Value;
 use crate::models::*;
 use crate::parse::{read_puzzle, read_task};
 use crate::puzzle::solve_puzzle;
+use crate::validate::validate_task;
 use crate::solve::solve_small_while;
+use crate::strategy::default_strategy;
 use chrono::prelude::*;
 use std::collections::HashMap;
 use std::{thread, time};
@@ -126,12 +128,37 @@ impl Client {
             }
         };
 
-        let puzzle = read_puzzle(&blockinfo.puzzle);
-        let task = read_task(&blockinfo.task, String::new());
+        let puzzle = match read_puzzle(&blockinfo.puzzle) {
+            Ok(puzzle) => puzzle,
+            Err(e) => {
+                eprintln!("puzzle of block {}: {}", block, e);
+                return false;
+            }
+        };
+        let task = match read_task(&blockinfo.task, String::new()) {
+            Ok(task) => task,
+            Err(e) => {
+                eprintln!("task of block {}: {}", block, e);
+                return false;
+            }
+        };
+        if let Err(e) = validate_task(&task) {
+            eprintln!("task of block {}: {}", block, e);
+            return false;
+        }
         info!("solving puzzle");
-        let puzzle_answer = solve_puzzle(puzzle);
+        // seeding with the block number makes a block's answers reproducible
+        let seed = block as u64;
+        let puzzle_answer = solve_puzzle(puzzle, seed);
         info!("solving task");
-        let task_answer = solve_small_while(task, &Buy::new(), std::time::Duration::from_secs(300));
+        let (task_answer, stats) = solve_small_while(
+            task,
+            &Buy::new(),
+            std::time::Duration::from_secs(300),
+            seed,
+            default_strategy(),
+        );
+        info!("task solved in {} turns (seed {})", stats.turns, stats.seed);
         info!("dumping");
 
         self.dump_task_answer(block, task_answer);
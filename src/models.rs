@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::cmp;
 use std::collections::HashMap;
 use std::fmt;
@@ -30,6 +31,11 @@ impl Place {
     pub fn hand(&self, r: Point) -> Point {
         self.0 + self.1.convert(r)
     }
+
+    // dense index of an in-bounds place on a map of the given width
+    pub fn index(&self, width: usize) -> usize {
+        (self.0.y as usize * width + self.0.x as usize) * 4 + self.1.index()
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
@@ -41,6 +47,14 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub fn index(self) -> usize {
+        match self {
+            Direction::Left => 0,
+            Direction::Up => 1,
+            Direction::Right => 2,
+            Direction::Down => 3,
+        }
+    }
     pub fn turn_right(self) -> Direction {
         match self {
             Direction::Left => Direction::Up,
@@ -97,7 +111,7 @@ impl Direction {
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -155,7 +169,7 @@ pub enum LineDirection {
     Horizontal,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Map(pub Vec<Point>);
 
 impl fmt::Display for Map {
@@ -186,6 +200,12 @@ impl Map {
         self.0.iter().map(|p| p.y).max().unwrap() as usize + 1
     }
 
+    // width and height of the map as the contest scores it, the largest
+    // coordinates rather than the grid size
+    pub fn size(&self) -> (usize, usize) {
+        (self.compute_width() - 1, self.compute_height() - 1)
+    }
+
     pub fn iter_lines(&self) -> Vec<(LineDirection, Point, Point)> {
         let mut iter = self.0.iter().cloned().cycle().peekable();
         let mut res = Vec::new();
@@ -241,7 +261,9 @@ impl Map {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+// JSON uses readable names instead of the contest codes written by Display
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BoosterType {
     NewHand,
     FastMove,
@@ -264,7 +286,7 @@ impl fmt::Display for BoosterType {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Booster {
     pub kind: BoosterType,
     pub point: Point,
@@ -282,7 +304,7 @@ impl fmt::Display for Booster {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Task {
     pub id: String,
     pub width: usize,
@@ -319,7 +341,8 @@ impl fmt::Display for Task {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Move {
     MoveUp,
     MoveDown,
@@ -330,7 +353,8 @@ pub enum Move {
     TurnRight,
 }
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Command {
     Move(Move),
     NewHand(Point),
@@ -362,7 +386,7 @@ impl fmt::Display for Command {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Puzzle {
     pub block: usize,
     pub epock: usize,
@@ -379,6 +403,30 @@ pub struct Puzzle {
     pub excludes: Vec<Point>,
 }
 
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{},{},{},{},{},{},{}#",
+            self.block,
+            self.epock,
+            self.max_length,
+            self.vertex_min,
+            self.vertex_max,
+            self.hand_count,
+            self.fast_count,
+            self.drill_count,
+            self.tele_count,
+            self.clone_count,
+            self.spawn_count
+        )?;
+        write!(f, "{}#", Map::new(self.includes.clone()))?;
+        write!(f, "{}", Map::new(self.excludes.clone()))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Commands(Vec<Vec<Command>>);
 
 impl Commands {
@@ -392,6 +440,15 @@ impl Commands {
     pub fn is_empty(&self) -> bool {
         self.0[0].is_empty()
     }
+    pub fn robot_count(&self) -> usize {
+        self.0.len()
+    }
+    pub fn get(&self, robot_idx: usize) -> Option<&[Command]> {
+        self.0.get(robot_idx).map(|cmds| cmds.as_slice())
+    }
+    pub fn iter(&self) -> impl Iterator<Item=&Vec<Command>> {
+        self.0.iter()
+    }
 }
 
 impl fmt::Display for Commands {
@@ -413,7 +470,7 @@ impl fmt::Display for Commands {
     }
 }
 
-#[derive(Default)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Buy(Vec<BoosterType>);
 
 impl Buy {
@@ -1,169 +1,331 @@
 use crate::models::*;
-use glob::glob;
-use std::fs::File;
-use std::io::Read;
-use std::iter::Peekable;
-use std::str::Chars;
+use crate::validate::validate_task;
+use glob::{glob, Pattern};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 pub struct Input {
     pub id: String,
+    // file stem, which output files are named after
+    pub name: String,
     pub task: Task,
 }
 
 impl Input {
-    fn new(input_file: &str, task: Task) -> Input {
-        let id = &input_file[5..8];
-        Input {
-            id: id.to_owned(),
-            task,
-        }
-    }
-
     pub fn output_file_name(&self) -> String {
-        format!("prob-{}.sol", self.id)
+        format!("{}.sol", self.name)
     }
     pub fn buy_file_name(&self) -> String {
-        format!("prob-{}.buy", self.id)
+        format!("{}.buy", self.name)
+    }
+    pub fn seed_file_name(&self) -> String {
+        format!("{}.seed", self.name)
     }
 }
 
+// The ID is the file stem without the "prob-" prefix of the contest tasks.
+pub fn read_input(path: &Path) -> Option<Input> {
+    let name = match path.file_stem().and_then(|s| s.to_str()) {
+        Some(name) => name.to_owned(),
+        None => {
+            eprintln!("{}: no file name", path.display());
+            return None;
+        }
+    };
+    let id = name.trim_start_matches("prob-").to_owned();
+    let task_str = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let task_str = task_str.trim_end();
+    let task = match read_task(task_str, id.clone()) {
+        Ok(task) => task,
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            return None;
+        }
+    };
+    match validate_task(&task) {
+        Ok(()) => Some(Input { id, name, task }),
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// Reads task files and every .desc file in directories, descending into
+// subdirectories if `recursive`. Later tasks with an already seen ID are skipped
+// since their outputs would collide.
+pub fn read_inputs<P: AsRef<Path>>(paths: &[P], recursive: bool) -> Vec<Input> {
+    let mut res: Vec<Input> = Vec::new();
+    for path in paths {
+        for file in find_files(path.as_ref(), recursive) {
+            if let Some(input) = read_input(&file) {
+                if res.iter().any(|i| i.name == input.name) {
+                    eprintln!("{}: duplicated task {}", file.display(), input.name);
+                    continue;
+                }
+                res.push(input);
+            }
+        }
+    }
+    res
+}
+
 pub fn read_all_inputs(dir: &str) -> Vec<Input> {
-    find_files(dir)
-        .into_iter()
-        .map(|f| {
-            let id = &f[5..8];
-            let task_path = format!("{}/{}", dir, f);
-            let mut task_file = File::open(&task_path).unwrap();
-            let mut task_str = String::new();
-            task_file.read_to_string(&mut task_str).unwrap();
-            let task_str = task_str.trim_end();
-            let task = read_task(&task_str, id.to_string());
-            Input::new(&f, task)
-        })
-        .collect::<Vec<_>>()
+    read_inputs(&[dir], false)
 }
 
-fn skip(iter: &mut Peekable<Chars>, expected: char) {
-    let c = iter.next().unwrap();
-    assert!(c == expected);
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    // byte offset of the unexpected character
+    pub offset: usize,
+    pub expected: String,
+    // None at the end of input
+    pub found: Option<char>,
 }
 
-fn skip_or_empty(iter: &mut Peekable<Chars>, expected: char) {
-    if let Some(c) = iter.next() {
-        assert!(c == expected);
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "byte {}: expected {}, found ", self.offset, self.expected)?;
+        match self.found {
+            Some(c) => write!(f, "{:?}", c),
+            None => write!(f, "end of input"),
+        }
     }
 }
 
-fn read_i32(iter: &mut Peekable<Chars>, last: char) -> i32 {
-    let mut x = 0i32;
-    let minus = if let Some('-') = iter.peek() {
-        iter.next();
-        true
-    } else {
-        false
-    };
-    loop {
-        let c = iter.next().unwrap();
-        if c.is_digit(10) {
-            x = x * 10 + i32::from(c as u8 - b'0');
+impl std::error::Error for ParseError {}
+
+struct Reader<'a> {
+    s: &'a str,
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(s: &'a str) -> Reader<'a> {
+        Reader { s, offset: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.offset..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T, ParseError> {
+        Err(ParseError {
+            offset: self.offset,
+            expected: expected.to_owned(),
+            found: self.peek(),
+        })
+    }
+
+    fn skip(&mut self, expected: char) -> Result<(), ParseError> {
+        if self.peek() == Some(expected) {
+            self.next();
+            Ok(())
         } else {
-            assert!(c == last);
-            return if minus { -x } else { x };
+            self.error(&format!("{:?}", expected))
         }
     }
-}
 
-fn read_usize(iter: &mut Peekable<Chars>, last: char) -> usize {
-    let mut x = 0usize;
-    assert!(*iter.peek().unwrap() != '-');
-    loop {
-        let c = iter.next().unwrap();
-        if c.is_digit(10) {
-            x = x * 10 + (c as u8 - b'0') as usize;
+    fn skip_or_empty(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => self.skip(expected),
+        }
+    }
+
+    fn read_digits(&mut self) -> Result<u64, ParseError> {
+        let mut x = 0u64;
+        let mut digits = 0;
+        while let Some(c) = self.peek() {
+            if !c.is_ascii_digit() {
+                break;
+            }
+            x = match x.checked_mul(10).and_then(|x| x.checked_add(u64::from(c as u8 - b'0'))) {
+                Some(x) => x,
+                None => return self.error("a smaller number"),
+            };
+            self.next();
+            digits += 1;
+        }
+        if digits == 0 {
+            return self.error("digit");
+        }
+        Ok(x)
+    }
+
+    fn read_i32(&mut self, last: char) -> Result<i32, ParseError> {
+        let minus = if self.peek() == Some('-') {
+            self.next();
+            true
         } else {
-            assert!(c == last);
-            return x;
+            false
+        };
+        let start = self.offset;
+        let x = self.read_digits()?;
+        if x > i32::MAX as u64 {
+            self.offset = start;
+            return self.error("a smaller number");
         }
+        self.skip(last)?;
+        let x = x as i32;
+        Ok(if minus { -x } else { x })
     }
-}
 
-fn read_point(iter: &mut Peekable<Chars>) -> Point {
-    skip(iter, '(');
-    let x = read_i32(iter, ',');
-    let y = read_i32(iter, ')');
-    Point::new(x, y)
-}
+    fn read_usize(&mut self, last: char) -> Result<usize, ParseError> {
+        let x = self.read_digits()?;
+        self.skip(last)?;
+        Ok(x as usize)
+    }
 
-fn read_map_internal(mut iter: &mut Peekable<Chars>) -> (Map, char) {
-    let mut points = Vec::new();
-    points.push(read_point(&mut iter));
-    while let Some(c) = iter.next() {
-        if c != ',' {
-            return (Map::new(points), c);
+    fn read_point(&mut self) -> Result<Point, ParseError> {
+        self.skip('(')?;
+        let x = self.read_i32(',')?;
+        let y = self.read_i32(')')?;
+        Ok(Point::new(x, y))
+    }
+
+    fn read_booster_type(&mut self) -> Result<BoosterType, ParseError> {
+        let booster_type = match self.peek() {
+            Some('B') => BoosterType::NewHand,
+            Some('F') => BoosterType::FastMove,
+            Some('L') => BoosterType::Drill,
+            Some('X') => BoosterType::Spawn,
+            Some('R') => BoosterType::Teleports,
+            Some('C') => BoosterType::Cloning,
+            _ => return self.error("booster code"),
+        };
+        self.next();
+        Ok(booster_type)
+    }
+
+    // boosters that can be bought, which excludes spawn points
+    fn read_bought_type(&mut self) -> Result<BoosterType, ParseError> {
+        if self.peek() == Some('X') {
+            return self.error("booster code other than 'X'");
         }
-        points.push(read_point(&mut iter));
+        self.read_booster_type()
     }
-    (Map::new(points), '\0')
-}
 
-fn read_map(mut iter: &mut Peekable<Chars>) -> Map {
-    let (m, c) = read_map_internal(&mut iter);
-    assert!(c == '#');
-    m
-}
+    fn expect_end(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => self.error("end of input"),
+        }
+    }
 
-fn read_initial(mut iter: &mut Peekable<Chars>) -> Point {
-    let p = read_point(&mut iter);
-    skip(iter, '#');
-    p
-}
+    // points separated by commas, stops before any other character
+    fn read_map_internal(&mut self) -> Result<Map, ParseError> {
+        let mut points = vec![self.read_point()?];
+        while self.peek() == Some(',') {
+            self.next();
+            points.push(self.read_point()?);
+        }
+        Ok(Map::new(points))
+    }
+
+    fn read_map(&mut self) -> Result<Map, ParseError> {
+        let m = self.read_map_internal()?;
+        self.skip('#')?;
+        Ok(m)
+    }
 
-fn read_obstacles(mut iter: &mut Peekable<Chars>) -> Vec<Map> {
-    let mut res = Vec::new();
-    if *iter.peek().unwrap() == '#' {
-        iter.next();
-        return res;
+    fn read_initial(&mut self) -> Result<Point, ParseError> {
+        let p = self.read_point()?;
+        self.skip('#')?;
+        Ok(p)
     }
 
-    loop {
-        let (m, c) = read_map_internal(&mut iter);
-        res.push(m);
-        if c == '#' {
-            break;
+    fn read_obstacles(&mut self) -> Result<Vec<Map>, ParseError> {
+        let mut res = Vec::new();
+        if self.peek() == Some('#') {
+            self.next();
+            return Ok(res);
         }
-        assert!(c == ';');
+
+        loop {
+            res.push(self.read_map_internal()?);
+            match self.peek() {
+                Some('#') => {
+                    self.next();
+                    break;
+                }
+                Some(';') => {
+                    self.next();
+                }
+                _ => return self.error("';' or '#'"),
+            }
+        }
+        Ok(res)
     }
-    res
-}
 
-fn read_boosters(mut iter: &mut Peekable<Chars>) -> Vec<Booster> {
-    let mut res = Vec::new();
-    while let Some(c) = iter.next() {
-        let booster_type = match c {
-            'B' => BoosterType::NewHand,
-            'F' => BoosterType::FastMove,
-            'L' => BoosterType::Drill,
-            'X' => BoosterType::Spawn,
-            'R' => BoosterType::Teleports,
-            'C' => BoosterType::Cloning,
-            _ => panic!("unknown type {}", c),
-        };
-        let point = read_point(&mut iter);
-        res.push(Booster::new(booster_type, point));
-        skip_or_empty(&mut iter, ';');
+    fn read_boosters(&mut self) -> Result<Vec<Booster>, ParseError> {
+        let mut res = Vec::new();
+        while self.peek().is_some() {
+            let booster_type = self.read_booster_type()?;
+            let point = self.read_point()?;
+            res.push(Booster::new(booster_type, point));
+            self.skip_or_empty(';')?;
+        }
+        Ok(res)
+    }
+
+    // commands of a single robot, stops before '#'
+    fn read_command(&mut self) -> Result<Vec<Command>, ParseError> {
+        let mut res = Vec::new();
+        while let Some(c) = self.peek() {
+            let cmd = match c {
+                'W' => Command::Move(Move::MoveUp),
+                'S' => Command::Move(Move::MoveDown),
+                'A' => Command::Move(Move::MoveLeft),
+                'D' => Command::Move(Move::MoveRight),
+                'Z' => Command::Move(Move::Noop),
+                'E' => Command::Move(Move::TurnRight),
+                'Q' => Command::Move(Move::TurnLeft),
+                'B' => {
+                    self.next();
+                    res.push(Command::NewHand(self.read_point()?));
+                    continue;
+                }
+                'F' => Command::FastWheel,
+                'L' => Command::Drill,
+                'R' => Command::ResetBeacon,
+                'T' => {
+                    self.next();
+                    res.push(Command::ShiftBeacon(self.read_point()?));
+                    continue;
+                }
+                'C' => Command::Cloning,
+                '#' => break,
+                _ => return self.error("command"),
+            };
+            self.next();
+            res.push(cmd);
+        }
+        Ok(res)
     }
-    res
 }
 
-pub fn read_task(s: &str, id: String) -> Task {
-    let mut iter = s.chars().peekable();
-    let map = read_map(&mut iter);
-    let initial = read_initial(&mut iter);
-    let obstacles = read_obstacles(&mut iter);
-    let boosters = read_boosters(&mut iter);
+pub fn read_task(s: &str, id: String) -> Result<Task, ParseError> {
+    let mut reader = Reader::new(s);
+    let map = reader.read_map()?;
+    let initial = reader.read_initial()?;
+    let obstacles = reader.read_obstacles()?;
+    let boosters = reader.read_boosters()?;
     let width = map.compute_width();
     let height = map.compute_height();
-    Task {
+    Ok(Task {
         id,
         width,
         height,
@@ -171,78 +333,60 @@ pub fn read_task(s: &str, id: String) -> Task {
         initial,
         obstacles,
         boosters,
-    }
+    })
 }
 
-fn read_command_internal(iter: &mut Peekable<Chars>) -> Vec<Command> {
-    let mut res = Vec::new();
-    while let Some(c) = iter.next() {
-        let cmd = match c {
-            'W' => Command::Move(Move::MoveUp),
-            'S' => Command::Move(Move::MoveDown),
-            'A' => Command::Move(Move::MoveLeft),
-            'D' => Command::Move(Move::MoveRight),
-            'Z' => Command::Move(Move::Noop),
-            'E' => Command::Move(Move::TurnRight),
-            'Q' => Command::Move(Move::TurnLeft),
-            'B' => {
-                let p = read_point(iter);
-                Command::NewHand(p)
-            }
-            'F' => Command::FastWheel,
-            'L' => Command::Drill,
-            'R' => Command::ResetBeacon,
-            'T' => {
-                let p = read_point(iter);
-                Command::ShiftBeacon(p)
-            }
-            'C' => Command::Cloning,
-            _ => unreachable!(),
-        };
-        res.push(cmd);
+// trailing whitespace such as a final newline is ignored
+pub fn read_commands(s: &str) -> Result<Commands, ParseError> {
+    let mut reader = Reader::new(s.trim_end());
+    let mut cmds = vec![reader.read_command()?];
+    while reader.peek() == Some('#') {
+        reader.next();
+        cmds.push(reader.read_command()?);
     }
-    res
-}
-
-fn read_command(s: &str) -> Vec<Command> {
-    read_command_internal(&mut s.chars().peekable())
+    Ok(Commands::new(cmds))
 }
 
-pub fn read_commands(s: &str) -> Commands {
-    Commands::new(s.split('#').map(|s| read_command(&s)).collect::<Vec<_>>())
-}
-
-fn find_files(input_root: &str) -> Vec<String> {
-    glob(&format!("{}/prob-*.desc", input_root))
+fn find_files(path: &Path, recursive: bool) -> Vec<PathBuf> {
+    if !path.is_dir() {
+        return vec![path.to_owned()];
+    }
+    let root = Pattern::escape(&path.to_string_lossy());
+    let pattern = if recursive {
+        format!("{}/**/*.desc", root)
+    } else {
+        format!("{}/*.desc", root)
+    };
+    glob(&pattern)
         .expect("glob pattern")
-        .map(|p| {
-            p.unwrap()
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string()
+        .filter_map(|p| match p {
+            Ok(p) => Some(p),
+            Err(e) => {
+                eprintln!("{}", e);
+                None
+            }
         })
-        .collect::<Vec<String>>()
+        .collect::<Vec<_>>()
 }
 
-pub fn read_puzzle(s: &str) -> Puzzle {
-    let mut iter = s.chars().peekable();
-    let block = read_usize(&mut iter, ',');
-    let epock = read_usize(&mut iter, ',');
-    let max_length = read_usize(&mut iter, ',');
-    let vertex_min = read_usize(&mut iter, ',');
-    let vertex_max = read_usize(&mut iter, ',');
-    let hand_count = read_usize(&mut iter, ',');
-    let fast_count = read_usize(&mut iter, ',');
-    let drill_count = read_usize(&mut iter, ',');
-    let tele_count = read_usize(&mut iter, ',');
-    let clone_count = read_usize(&mut iter, ',');
-    let spawn_count = read_usize(&mut iter, '#');
-    let (includes, c) = read_map_internal(&mut iter);
-    assert!(c == '#');
-    let (excludes, _) = read_map_internal(&mut iter);
-    Puzzle {
+// trailing whitespace such as a final newline is ignored
+pub fn read_puzzle(s: &str) -> Result<Puzzle, ParseError> {
+    let mut reader = Reader::new(s.trim_end());
+    let block = reader.read_usize(',')?;
+    let epock = reader.read_usize(',')?;
+    let max_length = reader.read_usize(',')?;
+    let vertex_min = reader.read_usize(',')?;
+    let vertex_max = reader.read_usize(',')?;
+    let hand_count = reader.read_usize(',')?;
+    let fast_count = reader.read_usize(',')?;
+    let drill_count = reader.read_usize(',')?;
+    let tele_count = reader.read_usize(',')?;
+    let clone_count = reader.read_usize(',')?;
+    let spawn_count = reader.read_usize('#')?;
+    let includes = reader.read_map()?;
+    let excludes = reader.read_map_internal()?;
+    reader.expect_end()?;
+    Ok(Puzzle {
         block,
         epock,
         max_length,
@@ -256,22 +400,104 @@ pub fn read_puzzle(s: &str) -> Puzzle {
         spawn_count,
         includes: includes.0,
         excludes: excludes.0,
-    }
+    })
 }
 
-pub fn read_buy(s: &str) -> Buy {
+// trailing whitespace such as a final newline is ignored
+pub fn read_buy(s: &str) -> Result<Buy, ParseError> {
+    let mut reader = Reader::new(s.trim_end());
     let mut buy = Buy::new();
-    for c in s.chars() {
-        let booster_type = match c {
-            'B' => BoosterType::NewHand,
-            'F' => BoosterType::FastMove,
-            'L' => BoosterType::Drill,
-            'X' => BoosterType::Spawn,
-            'R' => BoosterType::Teleports,
-            'C' => BoosterType::Cloning,
-            _ => panic!("unknown type {}", c),
-        };
-        buy.push(&booster_type);
+    while reader.peek().is_some() {
+        buy.push(&reader.read_bought_type()?);
+    }
+    Ok(buy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(offset: usize, expected: &str, found: Option<char>) -> ParseError {
+        ParseError {
+            offset,
+            expected: expected.to_owned(),
+            found,
+        }
+    }
+
+    const PUZZLE: &str = "1,1,150,400,1200,6,10,5,1,3,4#(73,61),(49,125)#(50,27),(54,14)";
+
+    #[test]
+    fn reads_a_task() {
+        let s = "(0,0),(6,0),(6,5),(0,5)#(1,1)#(2,2),(3,2),(3,3),(2,3)#B(4,1);X(0,4)";
+        let task = read_task(s, "1".to_owned()).unwrap();
+        assert_eq!((task.width, task.height), (7, 6));
+        assert_eq!(task.initial, Point::new(1, 1));
+        assert_eq!(task.obstacles.len(), 1);
+        assert_eq!(task.boosters.len(), 2);
+    }
+
+    #[test]
+    fn malformed_tasks() {
+        let read = |s: &str| read_task(s, "1".to_owned()).unwrap_err();
+        assert_eq!(read("(0,0),(1,0)"), error(11, "'#'", None));
+        assert_eq!(read("(0,0),(1;0)#"), error(8, "','", Some(';')));
+        assert_eq!(read("(0,0),(1,0)#(0,a)##"), error(15, "digit", Some('a')));
+        assert_eq!(read("(0,0)#(0,0)#(1,1)|"), error(17, "';' or '#'", Some('|')));
+        assert_eq!(read("(0,0)#(0,0)##Y(1,1)"), error(13, "booster code", Some('Y')));
+        assert_eq!(read("(0,0)#(0,0)##B(1,1)X"), error(19, "';'", Some('X')));
+        assert_eq!(read("(0,0)#(0,0)##B(1,99999999999)"), error(17, "a smaller number", Some('9')));
+    }
+
+    #[test]
+    fn reads_commands() {
+        let commands = read_commands("WB(1,-1)T(2,3)#C\n").unwrap();
+        assert_eq!(commands.robot_count(), 2);
+        assert_eq!(commands.get(0).map(|c| c.len()), Some(3));
+        assert_eq!(commands.get(1), Some(&[Command::Cloning][..]));
+    }
+
+    #[test]
+    fn malformed_solutions() {
+        assert_eq!(read_commands("WSX").unwrap_err(), error(2, "command", Some('X')));
+        assert_eq!(read_commands("WB(1,1").unwrap_err(), error(6, "')'", None));
+        assert_eq!(read_commands("T(1,-)").unwrap_err(), error(5, "digit", Some(')')));
+    }
+
+    #[test]
+    fn reads_a_puzzle() {
+        let puzzle = read_puzzle(&format!("{}\n", PUZZLE)).unwrap();
+        assert_eq!(puzzle.max_length, 150);
+        assert_eq!(puzzle.spawn_count, 4);
+        assert_eq!(puzzle.includes, vec![Point::new(73, 61), Point::new(49, 125)]);
+        assert_eq!(puzzle.excludes, vec![Point::new(50, 27), Point::new(54, 14)]);
+    }
+
+    #[test]
+    fn malformed_puzzles() {
+        assert_eq!(read_puzzle("1,1,150#").unwrap_err(), error(7, "','", Some('#')));
+        assert_eq!(
+            read_puzzle(&format!("{}#", PUZZLE)).unwrap_err(),
+            error(PUZZLE.len(), "end of input", Some('#'))
+        );
+        assert_eq!(
+            read_puzzle(&format!("{};(1,1)", PUZZLE)).unwrap_err(),
+            error(PUZZLE.len(), "end of input", Some(';'))
+        );
+    }
+
+    #[test]
+    fn reads_a_buy() {
+        let buy = read_buy("CBB\n").unwrap();
+        assert_eq!(buy.money(), 2000 + 1000 * 2);
+    }
+
+    #[test]
+    fn malformed_buys() {
+        assert_eq!(
+            read_buy("BX").unwrap_err(),
+            error(1, "booster code other than 'X'", Some('X'))
+        );
+        assert_eq!(read_buy("B,C").unwrap_err(), error(1, "booster code", Some(',')));
     }
-    buy
 }
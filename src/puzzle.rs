@@ -1,6 +1,8 @@
 use crate::models::*;
 use crate::utils::Range;
+use crate::validate::{validate_polygon, validate_task, Shape};
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use std::collections::{HashSet, VecDeque};
 
 fn construct_map_from_ranges(ranges: &[Range]) -> Map {
@@ -80,7 +82,7 @@ fn consume_points_for(source: &mut VecDeque<Point>, num: usize, kind: BoosterTyp
     res
 }
 
-pub fn solve_puzzle(puzzle: Puzzle) -> Option<Task> {
+pub fn solve_puzzle(puzzle: Puzzle, seed: u64) -> Option<Task> {
     let len = puzzle.max_length - 1;
     assert!(puzzle.includes.iter().all(|p| p.x < len as i32));
     assert!(puzzle.includes.iter().all(|p| p.y < len as i32));
@@ -162,6 +164,10 @@ pub fn solve_puzzle(puzzle: Puzzle) -> Option<Task> {
     }
 
     let map = construct_map_from_ranges(&ranges);
+    if let Err(e) = validate_polygon(&map, Shape::Map) {
+        eprintln!("NG: {}", e);
+        return None;
+    }
 
     if map.len() < puzzle.vertex_min {
         eprintln!(
@@ -187,7 +193,7 @@ pub fn solve_puzzle(puzzle: Puzzle) -> Option<Task> {
         return None;
     }
 
-    let mut rand = thread_rng();
+    let mut rand = StdRng::seed_from_u64(seed);
     points.shuffle(&mut rand);
     let mut point_source = points.into_iter().collect::<VecDeque<_>>();
     let initial = point_source.pop_front().unwrap();
@@ -233,6 +239,10 @@ pub fn solve_puzzle(puzzle: Puzzle) -> Option<Task> {
         obstacles: Vec::new(),
         boosters,
     };
+    if let Err(e) = validate_task(&task) {
+        eprintln!("NG: {}", e);
+        return None;
+    }
 
     Some(task)
 }
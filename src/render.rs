@@ -0,0 +1,492 @@
+use crate::models::*;
+use crate::sim::{Robot, SimError, Simulator};
+use crate::utils::BitMatrix;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// longest side of the rendered map in pixels
+const SVG_SIZE: usize = 800;
+const MIN_CELL_SIZE: usize = 2;
+const MAX_CELL_SIZE: usize = 20;
+// below this cell size booster letters are unreadable and left out
+const MIN_LABEL_SIZE: usize = 8;
+
+const BACKGROUND_COLOR: &str = "#3a3a3a";
+const UNPAINTED_COLOR: &str = "#ffffff";
+const PAINTED_COLOR: &str = "#f5d58a";
+const DRILLED_COLOR: &str = "#c9974a";
+const BEACON_COLOR: &str = "#7b3fa0";
+const START_COLOR: &str = "#1b9e4b";
+const ROBOT_COLORS: [&str; 8] = [
+    "#e6194b", "#4363d8", "#3cb44b", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+];
+
+pub fn robot_color(robot_idx: usize) -> &'static str {
+    ROBOT_COLORS[robot_idx % ROBOT_COLORS.len()]
+}
+
+// Escapes text for XML and HTML content.
+pub fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn booster_color(kind: &BoosterType) -> &'static str {
+    match kind {
+        BoosterType::NewHand => "#e0b000",
+        BoosterType::FastMove => "#8b5a2b",
+        BoosterType::Drill => "#2e8b57",
+        BoosterType::Teleports => "#4169e1",
+        BoosterType::Cloning => "#c71585",
+        BoosterType::Spawn => "#303030",
+    }
+}
+
+// boosters a robot can hold, in the order frames list their counts
+pub const INVENTORY_KINDS: [BoosterType; 5] = [
+    BoosterType::NewHand,
+    BoosterType::FastMove,
+    BoosterType::Drill,
+    BoosterType::Teleports,
+    BoosterType::Cloning,
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pickup {
+    pub point: Point,
+    pub kind: BoosterType,
+    pub robot: usize,
+    // turns elapsed when it got picked up, 0 if it lies under the start
+    pub turn: usize,
+}
+
+// State after a turn, with the changes made during it.
+pub struct Frame {
+    pub robots: Vec<Robot>,
+    pub painted: Vec<Point>,
+    pub drilled: Vec<Point>,
+    pub beacons: Vec<Point>,
+    pub inventory: Vec<usize>,
+    pub wrapped: usize,
+}
+
+// What happened while simulating a solution, as far as it got.
+pub struct Replay {
+    // the initial state followed by one frame per turn
+    pub frames: Vec<Frame>,
+    pub pickups: Vec<Pickup>,
+    pub beacons: Vec<Point>,
+    pub valid: BitMatrix,
+    pub wrapped: BitMatrix,
+    pub turns: usize,
+    pub error: Option<SimError>,
+}
+
+impl Replay {
+    pub fn new(task: &Task, buy: &Buy, commands: &Commands) -> Replay {
+        let mut sim = Simulator::new(task, buy);
+        let mut boosters = task
+            .boosters
+            .iter()
+            .filter(|b| b.kind != BoosterType::Spawn)
+            .map(|b| (b.point, b.kind.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut pickups = Vec::new();
+        pick_up(&sim, &mut boosters, &mut pickups, 0, task.initial, 0);
+
+        let mut wrapped = sim.wrapped().clone();
+        let mut valid = sim.valid().clone();
+        let mut painted = wrapped.clone();
+        painted.and(&valid);
+        let mut frames = vec![Frame {
+            robots: sim.robots().to_vec(),
+            painted: painted.iter_ones().collect(),
+            drilled: Vec::new(),
+            beacons: Vec::new(),
+            inventory: INVENTORY_KINDS.iter().map(|k| sim.inventory(k)).collect(),
+            wrapped: sim.area() - sim.remaining(),
+        }];
+
+        let mut error = None;
+        while !sim.is_finished() {
+            let turn = sim.turn() + 1;
+            let result = sim.step(commands);
+            // nobody moved on a turn without commands
+            if let Err(e @ SimError::Incomplete { .. }) = result {
+                error = Some(e);
+                break;
+            }
+            let prev_robots = &frames.last().unwrap().robots;
+            let mut painted = Vec::new();
+            let mut drilled = Vec::new();
+            for (idx, robot) in sim.robots().iter().enumerate() {
+                let mut passed = vec![robot.clone()];
+                if let Some(prev) = prev_robots.get(idx) {
+                    let (p, q) = (prev.place.point(), robot.place.point());
+                    // a fast move passes over the cell in between
+                    if (p.x - q.x).abs() + (p.y - q.y).abs() == 2 && (p.x == q.x || p.y == q.y) {
+                        let middle = Point::new((p.x + q.x) / 2, (p.y + q.y) / 2);
+                        let mut r = robot.clone();
+                        r.place = Place::new(middle, robot.place.dir());
+                        passed.insert(0, r);
+                    }
+                }
+                for r in &passed {
+                    let p = r.place.point();
+                    pick_up(&sim, &mut boosters, &mut pickups, idx, p, turn);
+                    if valid.try_set(p, true) == Some(false) {
+                        drilled.push(p);
+                    }
+                    for q in r.bodies() {
+                        if sim.wrapped().get(q) == Some(&true)
+                            && wrapped.try_set(q, true) == Some(false)
+                        {
+                            painted.push(q);
+                        }
+                    }
+                }
+            }
+            let installed = frames.iter().map(|f| f.beacons.len()).sum::<usize>();
+            frames.push(Frame {
+                robots: sim.robots().to_vec(),
+                painted,
+                drilled,
+                beacons: sim.beacons()[installed..].to_vec(),
+                inventory: INVENTORY_KINDS.iter().map(|k| sim.inventory(k)).collect(),
+                wrapped: sim.area() - sim.remaining(),
+            });
+            if let Err(e) = result {
+                error = Some(e);
+                break;
+            }
+        }
+        if error.is_none() && commands.robot_count() > sim.robots().len() {
+            error = Some(SimError::UnusedCommands {
+                robot: sim.robots().len(),
+            });
+        }
+
+        Replay {
+            frames,
+            pickups,
+            beacons: sim.beacons().to_vec(),
+            valid: sim.valid().clone(),
+            wrapped: sim.wrapped().clone(),
+            turns: sim.turn(),
+            error,
+        }
+    }
+
+    pub fn robot_count(&self) -> usize {
+        self.frames.last().map_or(0, |f| f.robots.len())
+    }
+
+    // positions of a robot from the turn it appears on
+    pub fn path(&self, robot_idx: usize) -> Vec<Point> {
+        self.frames
+            .iter()
+            .filter_map(|f| f.robots.get(robot_idx))
+            .map(|r| r.place.point())
+            .collect()
+    }
+}
+
+fn pick_up(
+    sim: &Simulator,
+    boosters: &mut HashMap<Point, BoosterType>,
+    pickups: &mut Vec<Pickup>,
+    robot: usize,
+    point: Point,
+    turn: usize,
+) {
+    if !boosters.contains_key(&point) || sim.booster_map().get(point) != Some(&None) {
+        return;
+    }
+    let kind = boosters.remove(&point).unwrap();
+    pickups.push(Pickup {
+        point,
+        kind,
+        robot,
+        turn,
+    });
+}
+
+// Maps contest coordinates, with y growing upwards, to SVG pixels.
+struct Canvas {
+    height: usize,
+    cell: usize,
+    out: String,
+}
+
+impl Canvas {
+    fn corner(&self, p: Point) -> (usize, usize) {
+        (
+            p.x as usize * self.cell,
+            (self.height - p.y as usize) * self.cell,
+        )
+    }
+
+    fn center(&self, p: Point) -> (f64, f64) {
+        let cell = self.cell as f64;
+        (
+            (p.x as f64 + 0.5) * cell,
+            (self.height as f64 - p.y as f64 - 0.5) * cell,
+        )
+    }
+
+    fn polygon(&mut self, map: &Map, style: &str) {
+        let points = map
+            .0
+            .iter()
+            .map(|&p| {
+                let (x, y) = self.corner(p);
+                format!("{},{}", x, y)
+            })
+            .collect::<Vec<_>>();
+        writeln!(
+            self.out,
+            r#"<polygon points="{}" {}/>"#,
+            points.join(" "),
+            style
+        )
+        .unwrap();
+    }
+
+    // one rectangle per horizontal run of cells in the same class
+    fn cells<F: Fn(Point) -> Option<&'static str>>(&mut self, width: usize, class: F) {
+        for y in 0..self.height {
+            let mut x = 0;
+            while x < width {
+                let color = class(Point::new(x as i32, y as i32));
+                let start = x;
+                while x < width && class(Point::new(x as i32, y as i32)) == color {
+                    x += 1;
+                }
+                if let Some(color) = color {
+                    let (px, py) = self.corner(Point::new(start as i32, y as i32 + 1));
+                    writeln!(
+                        self.out,
+                        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                        px,
+                        py,
+                        (x - start) * self.cell,
+                        self.cell,
+                        color
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+
+    fn booster(&mut self, b: &Booster, opacity: f64) {
+        let (cx, cy) = self.center(b.point);
+        writeln!(
+            self.out,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}" opacity="{}"/>"#,
+            cx,
+            cy,
+            self.cell as f64 * 0.4,
+            booster_color(&b.kind),
+            opacity
+        )
+        .unwrap();
+        if self.cell >= MIN_LABEL_SIZE {
+            writeln!(
+                self.out,
+                r##"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central" fill="#ffffff" opacity="{}">{}</text>"##,
+                cx,
+                cy,
+                self.cell as f64 * 0.6,
+                opacity,
+                b.kind
+            )
+            .unwrap();
+        }
+    }
+
+    // polyline of a robot path, broken up where the robot teleported
+    fn path(&mut self, path: &[Point], color: &str) {
+        let mut segments = vec![vec![path[0]]];
+        for w in path.windows(2) {
+            let (p, q) = (w[0], w[1]);
+            let dist = (p.x - q.x).abs() + (p.y - q.y).abs();
+            if dist > 2 || (p.x != q.x && p.y != q.y) {
+                segments.push(Vec::new());
+            }
+            segments.last_mut().unwrap().push(q);
+        }
+        let width = std::cmp::max(self.cell / 4, 1);
+        for segment in segments.iter().filter(|s| s.len() > 1) {
+            let points = segment
+                .iter()
+                .map(|&p| {
+                    let (x, y) = self.center(p);
+                    format!("{},{}", x, y)
+                })
+                .collect::<Vec<_>>();
+            writeln!(
+                self.out,
+                r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round" opacity="0.8"/>"#,
+                points.join(" "),
+                color,
+                width
+            )
+            .unwrap();
+        }
+    }
+}
+
+// Draws the task and, if given, the state reached by a replay on top of it.
+pub fn render_svg(task: &Task, replay: Option<&Replay>) -> String {
+    let side = std::cmp::max(std::cmp::max(task.width, task.height), 1);
+    let cell = (SVG_SIZE / side).clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+    let mut canvas = Canvas {
+        height: task.height,
+        cell,
+        out: String::new(),
+    };
+    let (width_px, height_px) = (task.width * cell, task.height * cell);
+
+    writeln!(
+        canvas.out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width_px, height_px, width_px, height_px
+    )
+    .unwrap();
+    let title = match replay {
+        Some(Replay { error: Some(e), .. }) => format!("{}: {}", task.id, e),
+        Some(replay) => format!("{}: {} turns", task.id, replay.turns),
+        None => task.id.clone(),
+    };
+    writeln!(canvas.out, "<title>{}</title>", escape_xml(&title)).unwrap();
+    writeln!(
+        canvas.out,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width_px, height_px, BACKGROUND_COLOR
+    )
+    .unwrap();
+
+    let initial = Simulator::new(task, &Buy::new());
+    let free = initial.valid();
+    match replay {
+        Some(replay) => canvas.cells(task.width, |p| {
+            if free.get(p) == Some(&true) {
+                if replay.wrapped.get(p) == Some(&true) {
+                    Some(PAINTED_COLOR)
+                } else {
+                    Some(UNPAINTED_COLOR)
+                }
+            } else if replay.valid.get(p) == Some(&true) {
+                Some(DRILLED_COLOR)
+            } else {
+                None
+            }
+        }),
+        None => canvas.cells(task.width, |p| {
+            if free.get(p) == Some(&true) {
+                Some(UNPAINTED_COLOR)
+            } else {
+                None
+            }
+        }),
+    }
+
+    let outline = format!(
+        r##"fill="none" stroke="#000000" stroke-width="{}""##,
+        std::cmp::max(cell / 6, 1)
+    );
+    canvas.polygon(&task.map, &outline);
+    for o in &task.obstacles {
+        canvas.polygon(o, &outline);
+    }
+
+    let picked = replay
+        .map(|r| r.pickups.iter().map(|p| p.point).collect::<Vec<_>>())
+        .unwrap_or_default();
+    for b in &task.boosters {
+        let opacity = if picked.contains(&b.point) { 0.3 } else { 1.0 };
+        canvas.booster(b, opacity);
+    }
+
+    let (x, y) = canvas.corner(Point::new(task.initial.x, task.initial.y + 1));
+    writeln!(
+        canvas.out,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="none" stroke="{}" stroke-width="{}"/>"#,
+        x,
+        y,
+        cell,
+        cell,
+        START_COLOR,
+        std::cmp::max(cell / 4, 1)
+    )
+    .unwrap();
+
+    if let Some(replay) = replay {
+        for idx in 0..replay.robot_count() {
+            canvas.path(&replay.path(idx), robot_color(idx));
+        }
+        for pickup in &replay.pickups {
+            let (cx, cy) = canvas.center(pickup.point);
+            writeln!(
+                canvas.out,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="{}" stroke-width="{}"><title>{} picked by robot {} on turn {}</title></circle>"#,
+                cx,
+                cy,
+                cell as f64 * 0.5,
+                robot_color(pickup.robot),
+                std::cmp::max(cell / 6, 1),
+                pickup.kind,
+                pickup.robot,
+                pickup.turn
+            )
+            .unwrap();
+        }
+        for &b in &replay.beacons {
+            let (cx, cy) = canvas.center(b);
+            let r = cell as f64 * 0.45;
+            writeln!(
+                canvas.out,
+                r#"<polygon points="{},{} {},{} {},{} {},{}" fill="{}"/>"#,
+                cx,
+                cy - r,
+                cx + r,
+                cy,
+                cx,
+                cy + r,
+                cx - r,
+                cy,
+                BEACON_COLOR
+            )
+            .unwrap();
+        }
+        for (idx, robot) in replay.frames.last().unwrap().robots.iter().enumerate() {
+            let (cx, cy) = canvas.center(robot.place.point());
+            writeln!(
+                canvas.out,
+                r##"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="#000000"/>"##,
+                cx,
+                cy,
+                cell as f64 * 0.35,
+                robot_color(idx)
+            )
+            .unwrap();
+        }
+    }
+
+    writeln!(canvas.out, "</svg>").unwrap();
+    canvas.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::read_task;
+
+    #[test]
+    fn title_is_escaped() {
+        let task = read_task("(0,0),(2,0),(2,2),(0,2)#(0,0)##", "a<b>&c".to_owned()).unwrap();
+        let svg = render_svg(&task, None);
+        assert!(svg.contains("<title>a&lt;b&gt;&amp;c</title>"));
+    }
+}
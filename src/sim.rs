@@ -0,0 +1,607 @@
+use crate::models::*;
+use crate::utils::{free_cells, is_visible, BitMatrix, Matrix};
+
+use std::collections::HashMap;
+use std::fmt;
+
+pub const FAST_WHEEL_TURNS: usize = 50;
+pub const DRILL_TURNS: usize = 30;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Violation {
+    Blocked(Point),
+    NoBooster(BoosterType),
+    HandExists(Point),
+    HandNotAdjacent(Point),
+    BeaconExists(Point),
+    BeaconOnSpawn(Point),
+    NoBeacon(Point),
+    NotOnSpawn(Point),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Blocked(p) => write!(f, "cannot move into {}", p),
+            Violation::NoBooster(b) => write!(f, "no {} booster available", b),
+            Violation::HandExists(p) => write!(f, "manipulator {} already attached", p),
+            Violation::HandNotAdjacent(p) => write!(f, "manipulator {} is not adjacent", p),
+            Violation::BeaconExists(p) => write!(f, "beacon already installed at {}", p),
+            Violation::BeaconOnSpawn(p) => write!(f, "cannot install a beacon on spawn point {}", p),
+            Violation::NoBeacon(p) => write!(f, "no beacon at {}", p),
+            Violation::NotOnSpawn(p) => write!(f, "{} is not a spawn point", p),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SimError {
+    Invalid {
+        turn: usize,
+        robot: usize,
+        command: Command,
+        violation: Violation,
+    },
+    Incomplete {
+        turn: usize,
+        remaining: usize,
+    },
+    UnusedCommands {
+        robot: usize,
+    },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SimError::Invalid {
+                turn,
+                robot,
+                command,
+                violation,
+            } => write!(
+                f,
+                "turn {}: robot {}: command {}: {}",
+                turn, robot, command, violation
+            ),
+            SimError::Incomplete { turn, remaining } => write!(
+                f,
+                "turn {}: commands exhausted with {} cells unwrapped",
+                turn, remaining
+            ),
+            SimError::UnusedCommands { robot } => {
+                write!(f, "commands given for robot {} which never exists", robot)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Robot {
+    pub place: Place,
+    pub hands: Vec<Point>,
+    pub fast_turns: usize,
+    pub drill_turns: usize,
+    first_turn: usize,
+}
+
+impl Robot {
+    fn new(point: Point, first_turn: usize) -> Robot {
+        Robot {
+            place: Place::new(point, Direction::Right),
+            hands: vec![Point::new(1, 1), Point::new(1, 0), Point::new(1, -1)],
+            fast_turns: 0,
+            drill_turns: 0,
+            first_turn,
+        }
+    }
+
+    // absolute positions of the body and every manipulator
+    pub fn bodies(&self) -> Vec<Point> {
+        let mut res = vec![self.place.point()];
+        res.extend(self.hands.iter().map(|&h| self.place.hand(h)));
+        res
+    }
+}
+
+// Replays commands under the contest rules.
+pub struct Simulator<'a> {
+    task: &'a Task,
+    turn: usize,
+    area: usize,
+    valid: BitMatrix,
+    wrapped: BitMatrix,
+    remaining: usize,
+    booster_map: Matrix<Option<BoosterType>>,
+    inventory: HashMap<BoosterType, usize>,
+    collected: Vec<BoosterType>,
+    beacons: Vec<Point>,
+    robots: Vec<Robot>,
+}
+
+impl<'a> Simulator<'a> {
+    pub fn new(task: &'a Task, buy: &Buy) -> Simulator<'a> {
+        let width = task.width;
+        let height = task.height;
+
+        let valid = free_cells(task);
+        let mut booster_map = Matrix::new(width, height, None);
+
+        // cells outside the map and obstacles count as wrapped
+        let remaining = valid.count_ones();
+        let mut wrapped = BitMatrix::new(width, height, true);
+        wrapped.andnot(&valid);
+
+        for b in &task.boosters {
+            booster_map.set(b.point, Some(b.kind.clone()));
+        }
+
+        let mut inventory = HashMap::new();
+        for b in buy.iter() {
+            *inventory.entry(b.clone()).or_insert(0) += 1;
+        }
+
+        let mut sim = Simulator {
+            task,
+            turn: 0,
+            area: remaining,
+            valid,
+            wrapped,
+            remaining,
+            booster_map,
+            inventory,
+            collected: Vec::new(),
+            beacons: Vec::new(),
+            robots: vec![Robot::new(task.initial, 0)],
+        };
+
+        // a booster under the initial position is usable from the first turn
+        sim.pick_booster(task.initial);
+        sim.store_collected();
+        sim.wrap(0);
+        sim
+    }
+
+    pub fn task(&self) -> &Task {
+        self.task
+    }
+
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    // number of cells to be wrapped, excluding obstacles
+    pub fn area(&self) -> usize {
+        self.area
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining == 0
+    }
+
+    pub fn robots(&self) -> &[Robot] {
+        &self.robots
+    }
+
+    pub fn beacons(&self) -> &[Point] {
+        &self.beacons
+    }
+
+    pub fn valid(&self) -> &BitMatrix {
+        &self.valid
+    }
+
+    pub fn wrapped(&self) -> &BitMatrix {
+        &self.wrapped
+    }
+
+    pub fn booster_map(&self) -> &Matrix<Option<BoosterType>> {
+        &self.booster_map
+    }
+
+    pub fn inventory(&self, kind: &BoosterType) -> usize {
+        *self.inventory.get(kind).unwrap_or(&0)
+    }
+
+    // Runs all commands and returns the turn on which the map gets fully wrapped.
+    pub fn run(mut self, commands: &Commands) -> Result<usize, SimError> {
+        while !self.is_finished() {
+            self.step(commands)?;
+        }
+        match commands.robot_count() {
+            n if n > self.robots.len() => Err(SimError::UnusedCommands {
+                robot: self.robots.len(),
+            }),
+            _ => Ok(self.turn),
+        }
+    }
+
+    // Executes a single turn for every robot. Returns true if the map got fully wrapped.
+    pub fn step(&mut self, commands: &Commands) -> Result<bool, SimError> {
+        let turn = self.turn;
+        let robots_len = self.robots.len();
+        let mut executed = false;
+        for idx in 0..robots_len {
+            let offset = turn - self.robots[idx].first_turn;
+            let cmd = match commands.get(idx).and_then(|cmds| cmds.get(offset)) {
+                Some(cmd) => cmd,
+                None => continue,
+            };
+            executed = true;
+            if let Err(violation) = self.execute(idx, cmd) {
+                return Err(SimError::Invalid {
+                    turn,
+                    robot: idx,
+                    command: cmd.clone(),
+                    violation,
+                });
+            }
+            if self.is_finished() {
+                break;
+            }
+        }
+        if !executed {
+            return Err(SimError::Incomplete {
+                turn,
+                remaining: self.remaining,
+            });
+        }
+        self.store_collected();
+        self.turn += 1;
+        Ok(self.is_finished())
+    }
+
+    fn execute(&mut self, idx: usize, cmd: &Command) -> Result<(), Violation> {
+        match cmd {
+            Command::Move(m) => match m {
+                Move::MoveUp | Move::MoveDown | Move::MoveLeft | Move::MoveRight => {
+                    let steps = if self.robots[idx].fast_turns > 0 { 2 } else { 1 };
+                    for step in 0..steps {
+                        let next = self.robots[idx].place.point().move_with(m);
+                        if !self.try_enter(idx, next) {
+                            if step == 0 {
+                                return Err(Violation::Blocked(next));
+                            }
+                            break;
+                        }
+                        self.robots[idx].place = self.robots[idx].place.move_with(m);
+                        self.pick_booster(next);
+                        self.wrap(idx);
+                    }
+                }
+                Move::TurnLeft | Move::TurnRight | Move::Noop => {
+                    self.robots[idx].place = self.robots[idx].place.move_with(m);
+                }
+            },
+            Command::NewHand(p) => {
+                let robot = &self.robots[idx];
+                let diff = robot.place.dir().reconvert(*p);
+                if diff == Point::new(0, 0) || robot.hands.contains(&diff) {
+                    return Err(Violation::HandExists(*p));
+                }
+                let adjacent = robot
+                    .hands
+                    .iter()
+                    .chain(std::iter::once(&Point::new(0, 0)))
+                    .any(|h| (h.x - diff.x).abs() + (h.y - diff.y).abs() == 1);
+                if !adjacent {
+                    return Err(Violation::HandNotAdjacent(*p));
+                }
+                self.consume(BoosterType::NewHand)?;
+                self.robots[idx].hands.push(diff);
+            }
+            Command::FastWheel => {
+                self.consume(BoosterType::FastMove)?;
+                self.robots[idx].fast_turns = FAST_WHEEL_TURNS;
+            }
+            Command::Drill => {
+                self.consume(BoosterType::Drill)?;
+                self.robots[idx].drill_turns = DRILL_TURNS;
+            }
+            Command::ResetBeacon => {
+                let p = self.robots[idx].place.point();
+                if self.beacons.contains(&p) {
+                    return Err(Violation::BeaconExists(p));
+                }
+                if self.booster_map.get(p) == Some(&Some(BoosterType::Spawn)) {
+                    return Err(Violation::BeaconOnSpawn(p));
+                }
+                self.consume(BoosterType::Teleports)?;
+                self.beacons.push(p);
+            }
+            Command::ShiftBeacon(p) => {
+                if !self.beacons.contains(p) {
+                    return Err(Violation::NoBeacon(*p));
+                }
+                let dir = self.robots[idx].place.dir();
+                self.robots[idx].place = Place::new(*p, dir);
+                self.pick_booster(*p);
+            }
+            Command::Cloning => {
+                let p = self.robots[idx].place.point();
+                if self.booster_map.get(p) != Some(&Some(BoosterType::Spawn)) {
+                    return Err(Violation::NotOnSpawn(p));
+                }
+                self.consume(BoosterType::Cloning)?;
+                self.robots.push(Robot::new(p, self.turn + 1));
+            }
+        }
+        self.wrap(idx);
+
+        // activation turn counts towards the duration
+        let robot = &mut self.robots[idx];
+        robot.fast_turns = robot.fast_turns.saturating_sub(1);
+        robot.drill_turns = robot.drill_turns.saturating_sub(1);
+        Ok(())
+    }
+
+    fn try_enter(&mut self, idx: usize, p: Point) -> bool {
+        match self.valid.get(p) {
+            Some(true) => true,
+            Some(false) if self.robots[idx].drill_turns > 0 => {
+                // drilled cells become free space, wrapped as the robot stands on them
+                self.valid.set(p, true);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn consume(&mut self, kind: BoosterType) -> Result<(), Violation> {
+        match self.inventory.get_mut(&kind) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                Ok(())
+            }
+            _ => Err(Violation::NoBooster(kind)),
+        }
+    }
+
+    fn pick_booster(&mut self, p: Point) {
+        if let Some(Some(kind)) = self.booster_map.get(p) {
+            if *kind != BoosterType::Spawn {
+                self.collected.push(kind.clone());
+                self.booster_map.set(p, None);
+            }
+        }
+    }
+
+    // boosters picked up during a turn become usable on the next one
+    fn store_collected(&mut self) {
+        for kind in self.collected.drain(..) {
+            *self.inventory.entry(kind).or_insert(0) += 1;
+        }
+    }
+
+    fn wrap(&mut self, idx: usize) {
+        let place = self.robots[idx].place;
+        let mut targets = vec![place.point()];
+        for &diff in &self.robots[idx].hands {
+            let hand = place.hand(diff);
+            if is_visible(place.point(), hand, |p| self.valid.get(p) == Some(&true)) {
+                targets.push(hand);
+            }
+        }
+        for p in targets {
+            if let Some(false) = self.wrapped.get(p) {
+                self.wrapped.set(p, true);
+                self.remaining -= 1;
+            }
+        }
+    }
+}
+
+pub fn simulate(task: &Task, buy: &Buy, commands: &Commands) -> Result<usize, SimError> {
+    Simulator::new(task, buy).run(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{read_buy, read_commands, read_task};
+
+    fn task(s: &str) -> Task {
+        read_task(s, "test".to_owned()).unwrap()
+    }
+
+    fn buy(s: &str) -> Buy {
+        read_buy(s).unwrap()
+    }
+
+    fn commands(s: &str) -> Commands {
+        read_commands(s).unwrap()
+    }
+
+    // steps until the map is wrapped or the commands run out
+    fn run_all(sim: &mut Simulator, cmds: &Commands) -> Result<(), SimError> {
+        loop {
+            match sim.step(cmds) {
+                Ok(true) | Err(SimError::Incomplete { .. }) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn violation(res: Result<(), SimError>) -> (usize, usize, Violation) {
+        match res {
+            Err(SimError::Invalid {
+                turn,
+                robot,
+                violation,
+                ..
+            }) => (turn, robot, violation),
+            res => panic!("expected a violation, got {:?}", res),
+        }
+    }
+
+    #[test]
+    fn fast_wheel_lasts_from_activation() {
+        let task = task("(0,0),(200,0),(200,1),(0,1)#(0,0)##");
+        let buy = buy("F");
+        // the activation turn and 48 idle ones leave a single fast turn
+        let cmds = commands(&format!("F{}DD", "Z".repeat(48)));
+        let mut sim = Simulator::new(&task, &buy);
+        sim.step(&cmds).unwrap();
+        assert_eq!(sim.robots()[0].fast_turns, FAST_WHEEL_TURNS - 1);
+        for _ in 0..48 {
+            sim.step(&cmds).unwrap();
+        }
+        assert_eq!(sim.robots()[0].fast_turns, 1);
+        sim.step(&cmds).unwrap();
+        assert_eq!(sim.robots()[0].place.point(), Point::new(2, 0));
+        assert_eq!(sim.robots()[0].fast_turns, 0);
+        sim.step(&cmds).unwrap();
+        assert_eq!(sim.robots()[0].place.point(), Point::new(3, 0));
+    }
+
+    #[test]
+    fn drill_lasts_from_activation() {
+        let task = task("(0,0),(40,0),(40,3),(0,3)#(1,1)#(2,1),(38,1),(38,2),(2,2)#");
+        let buy = buy("L");
+        let cmds = commands(&format!("L{}", "D".repeat(DRILL_TURNS)));
+        let mut sim = Simulator::new(&task, &buy);
+        let (turn, robot, v) = violation(run_all(&mut sim, &cmds));
+        // 29 cells get drilled after the activation turn
+        assert_eq!((turn, robot), (DRILL_TURNS, 0));
+        assert_eq!(v, Violation::Blocked(Point::new(31, 1)));
+        assert_eq!(sim.robots()[0].place.point(), Point::new(30, 1));
+        assert_eq!(sim.valid().get(Point::new(30, 1)), Some(&true));
+        assert_eq!(sim.wrapped().get(Point::new(30, 1)), Some(&true));
+    }
+
+    #[test]
+    fn picked_booster_is_usable_next_turn() {
+        let task = task("(0,0),(10,0),(10,1),(0,1)#(0,0)##X(0,0);F(1,0)");
+        let buy = buy("C");
+
+        // the clone acts from turn 1, when the first robot picks up the wheels
+        let cmds = commands("CD#F");
+        let mut sim = Simulator::new(&task, &buy);
+        let (turn, robot, v) = violation(run_all(&mut sim, &cmds));
+        assert_eq!((turn, robot), (1, 1));
+        assert_eq!(v, Violation::NoBooster(BoosterType::FastMove));
+
+        let cmds = commands("CDZ#ZF");
+        let mut sim = Simulator::new(&task, &buy);
+        run_all(&mut sim, &cmds).unwrap();
+        assert_eq!(sim.robots()[1].fast_turns, FAST_WHEEL_TURNS - 1);
+    }
+
+    #[test]
+    fn booster_under_initial_position_is_usable_at_once() {
+        let task = task("(0,0),(10,0),(10,1),(0,1)#(0,0)##F(0,0)");
+        let mut sim = Simulator::new(&task, &Buy::new());
+        sim.step(&commands("F")).unwrap();
+        assert_eq!(sim.robots()[0].fast_turns, FAST_WHEEL_TURNS - 1);
+    }
+
+    #[test]
+    fn new_hand_must_be_adjacent() {
+        let task = task("(0,0),(10,0),(10,10),(0,10)#(5,5)##");
+        let buy = buy("BB");
+
+        let mut sim = Simulator::new(&task, &buy);
+        run_all(&mut sim, &commands("B(1,2)")).unwrap();
+        assert!(sim.robots()[0].hands.contains(&Point::new(1, 2)));
+        assert_eq!(sim.wrapped().get(Point::new(6, 7)), Some(&true));
+
+        let mut sim = Simulator::new(&task, &buy);
+        let (_, _, v) = violation(run_all(&mut sim, &commands("B(0,2)")));
+        assert_eq!(v, Violation::HandNotAdjacent(Point::new(0, 2)));
+
+        let mut sim = Simulator::new(&task, &buy);
+        let (_, _, v) = violation(run_all(&mut sim, &commands("B(1,0)")));
+        assert_eq!(v, Violation::HandExists(Point::new(1, 0)));
+
+        // facing down, the initial manipulators are below the robot
+        let mut sim = Simulator::new(&task, &buy);
+        run_all(&mut sim, &commands("EB(-2,-1)")).unwrap();
+        assert!(sim.robots()[0].bodies().contains(&Point::new(3, 4)));
+        let mut sim = Simulator::new(&task, &buy);
+        let (_, _, v) = violation(run_all(&mut sim, &commands("EB(1,2)")));
+        assert_eq!(v, Violation::HandNotAdjacent(Point::new(1, 2)));
+    }
+
+    #[test]
+    fn new_hand_needs_a_booster() {
+        let task = task("(0,0),(10,0),(10,10),(0,10)#(5,5)##");
+        let mut sim = Simulator::new(&task, &Buy::new());
+        let (_, _, v) = violation(run_all(&mut sim, &commands("B(1,2)")));
+        assert_eq!(v, Violation::NoBooster(BoosterType::NewHand));
+    }
+
+    #[test]
+    fn beacons_and_teleport() {
+        let task = task("(0,0),(10,0),(10,1),(0,1)#(0,0)##X(5,0)");
+
+        let mut sim = Simulator::new(&task, &buy("R"));
+        run_all(&mut sim, &commands("RDDT(0,0)")).unwrap();
+        assert_eq!(sim.beacons(), &[Point::new(0, 0)]);
+        assert_eq!(sim.robots()[0].place.point(), Point::new(0, 0));
+        assert_eq!(sim.turn(), 4);
+
+        let mut sim = Simulator::new(&task, &buy("RR"));
+        let (turn, _, v) = violation(run_all(&mut sim, &commands("RR")));
+        assert_eq!(turn, 1);
+        assert_eq!(v, Violation::BeaconExists(Point::new(0, 0)));
+
+        let mut sim = Simulator::new(&task, &buy("R"));
+        let (_, _, v) = violation(run_all(&mut sim, &commands("DDDDDR")));
+        assert_eq!(v, Violation::BeaconOnSpawn(Point::new(5, 0)));
+
+        let mut sim = Simulator::new(&task, &buy("R"));
+        let (_, _, v) = violation(run_all(&mut sim, &commands("RDT(2,0)")));
+        assert_eq!(v, Violation::NoBeacon(Point::new(2, 0)));
+    }
+
+    #[test]
+    fn cloning_needs_a_spawn_point() {
+        let task = task("(0,0),(10,0),(10,1),(0,1)#(0,0)##X(0,0);X(3,0)");
+
+        // the clone starts at the spawn point on the next turn
+        let mut sim = Simulator::new(&task, &buy("C"));
+        run_all(&mut sim, &commands("CZ#D")).unwrap();
+        assert_eq!(sim.robots().len(), 2);
+        assert_eq!(sim.robots()[1].place.point(), Point::new(1, 0));
+        assert_eq!(sim.robots()[1].place.dir(), Direction::Right);
+
+        let mut sim = Simulator::new(&task, &buy("C"));
+        let (_, _, v) = violation(run_all(&mut sim, &commands("DC")));
+        assert_eq!(v, Violation::NotOnSpawn(Point::new(1, 0)));
+
+        let mut sim = Simulator::new(&task, &Buy::new());
+        let (_, _, v) = violation(run_all(&mut sim, &commands("C")));
+        assert_eq!(v, Violation::NoBooster(BoosterType::Cloning));
+
+        assert_eq!(
+            simulate(&task, &buy("C"), &commands("C#D")),
+            Err(SimError::Incomplete {
+                turn: 2,
+                remaining: 7
+            })
+        );
+    }
+
+    #[test]
+    fn manipulators_need_line_of_sight() {
+        // the obstacle hides the cell right behind it
+        let task = task("(0,0),(5,0),(5,3),(0,3)#(1,1)#(2,1),(3,1),(3,2),(2,2)#");
+        let mut sim = Simulator::new(&task, &buy("B"));
+        run_all(&mut sim, &commands("B(2,0)")).unwrap();
+        assert!(sim.robots()[0].hands.contains(&Point::new(2, 0)));
+        assert_eq!(sim.wrapped().get(Point::new(2, 2)), Some(&true));
+        assert_eq!(sim.wrapped().get(Point::new(2, 0)), Some(&true));
+        assert_eq!(sim.wrapped().get(Point::new(3, 1)), Some(&false));
+
+        // one step up the obstacle no longer stands in the way
+        run_all(&mut sim, &commands("B(2,0)W")).unwrap();
+        assert_eq!(sim.wrapped().get(Point::new(3, 2)), Some(&true));
+    }
+}
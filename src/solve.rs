@@ -1,11 +1,24 @@
+use crate::layout::{is_attachable, LayoutKind};
 use crate::models::*;
-use crate::utils::Matrix;
+use crate::sim::{DRILL_TURNS, FAST_WHEEL_TURNS};
+use crate::strategy::{default_strategy, Strategy};
+use crate::utils::{free_cells, is_visible, BitMatrix, Matrix};
 
 use rand::prelude::*;
-use std::collections::{HashMap, HashSet, VecDeque};
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
 
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Left,
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+];
+
 #[derive(Clone, Eq, PartialEq)]
 pub struct Robot {
     current_place: Place,
@@ -13,32 +26,20 @@ pub struct Robot {
     new_bodies: VecDeque<Point>,
     commands: Vec<Command>,
     executed: Vec<Command>,
+    fast_remaining: usize,
+    drill_remaining: usize,
+    // unwrapped cells each place would wrap with the current hands, indexed
+    // by `Place::index`; built lazily on the first search
+    wrap_counts: Option<Vec<u8>>,
 }
 
 impl Robot {
-    fn clone_from(robot: &Robot) -> Robot {
-        let current_place = robot.current_place;
-
-        let bodies_diff = vec![
-            Point::new(0, 0),
-            Point::new(1, 1),
-            Point::new(1, 0),
-            Point::new(1, -1),
-        ];
-        let new_bodies = VecDeque::from(vec![
-            Point::new(2, 0),
-            Point::new(3, 0),
-            Point::new(4, 0),
-            Point::new(5, 0),
-            Point::new(6, 0),
-            Point::new(7, 0),
-            Point::new(8, 0),
-            Point::new(9, 0),
-            Point::new(10, 0),
-            Point::new(11, 0),
-            Point::new(12, 0),
-            Point::new(13, 0),
-        ]);
+    fn clone_from(robot: &Robot, layout: LayoutKind) -> Robot {
+        // a cloned robot starts facing right like the initial one
+        let current_place = Place::new(robot.current_place.point(), Direction::Right);
+
+        let bodies_diff = LayoutKind::initial_bodies();
+        let new_bodies = VecDeque::from(layout.new_hands());
 
         let commands = robot.commands.clone();
         let executed = Vec::new();
@@ -48,35 +49,19 @@ impl Robot {
             new_bodies,
             commands,
             executed,
+            fast_remaining: 0,
+            drill_remaining: 0,
+            wrap_counts: None,
         }
     }
 
-    fn initialize(task: &Task) -> Robot {
+    fn initialize(task: &Task, layout: LayoutKind) -> Robot {
         let current_point = task.initial;
         let current_dir = Direction::Right;
         let current_place = Place::new(current_point, current_dir);
 
-        let bodies_diff = vec![
-            Point::new(0, 0),
-            Point::new(1, 1),
-            Point::new(1, 0),
-            Point::new(1, -1),
-        ];
-
-        let new_bodies = VecDeque::from(vec![
-            Point::new(2, 0),
-            Point::new(3, 0),
-            Point::new(4, 0),
-            Point::new(5, 0),
-            Point::new(6, 0),
-            Point::new(7, 0),
-            Point::new(8, 0),
-            Point::new(9, 0),
-            Point::new(10, 0),
-            Point::new(11, 0),
-            Point::new(12, 0),
-            Point::new(13, 0),
-        ]);
+        let bodies_diff = LayoutKind::initial_bodies();
+        let new_bodies = VecDeque::from(layout.new_hands());
 
         let commands = Vec::new();
         let executed = Vec::new();
@@ -86,6 +71,9 @@ impl Robot {
             new_bodies,
             commands,
             executed,
+            fast_remaining: 0,
+            drill_remaining: 0,
+            wrap_counts: None,
         }
     }
 
@@ -94,44 +82,105 @@ impl Robot {
     }
 
     fn consume_new_hand(&mut self) -> Option<Point> {
-        self.new_bodies.pop_front()
+        while let Some(p) = self.new_bodies.pop_front() {
+            if is_attachable(&self.bodies_diff, p) {
+                return Some(p);
+            }
+        }
+        None
+    }
+
+    // the activation turn counts towards the duration as in the simulator
+    fn consume_turn(&mut self) {
+        self.fast_remaining = self.fast_remaining.saturating_sub(1);
+        self.drill_remaining = self.drill_remaining.saturating_sub(1);
     }
 }
 
-#[derive(Clone, Eq, PartialEq)]
+// Search buffers indexed by `Place::index`, reused across searches. An entry is
+// only meaningful if its stamp equals the current generation.
+#[derive(Clone, Default)]
+struct PathBuffer {
+    generation: u32,
+    stamps: Vec<u32>,
+    costs: Vec<u32>,
+    parents: Vec<(Place, Command)>,
+    queue: VecDeque<Place>,
+}
+
+impl PathBuffer {
+    fn new(width: usize, height: usize) -> PathBuffer {
+        let len = width * height * 4;
+        let origin = Place::new(Point::new(0, 0), Direction::Right);
+        PathBuffer {
+            generation: 0,
+            stamps: vec![0; len],
+            costs: vec![0; len],
+            parents: vec![(origin, Command::Move(Move::Noop)); len],
+            queue: VecDeque::new(),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            for stamp in self.stamps.iter_mut() {
+                *stamp = 0;
+            }
+            self.generation = 1;
+        }
+        self.queue.clear();
+    }
+
+    fn is_visited(&self, idx: usize) -> bool {
+        self.stamps[idx] == self.generation
+    }
+
+    fn visit(&mut self, idx: usize, parent: Place, cmd: Command, cost: u32) {
+        self.stamps[idx] = self.generation;
+        self.costs[idx] = cost;
+        self.parents[idx] = (parent, cmd);
+    }
+}
+
+#[derive(Clone)]
 pub struct State<'a> {
     task: &'a Task,
+    strategy: &'a dyn Strategy,
+    rng: StdRng,
+    layout: LayoutKind,
     turn: usize,
-    valid: Matrix<bool>,
-    passed: Matrix<bool>,
+    valid: BitMatrix,
+    passed: BitMatrix,
     booster_map: Matrix<Option<BoosterType>>,
     remaining_hand: usize,
     remaining_clone: usize,
     remaining_pass: usize,
     hand_count: usize,
     clone_count: usize,
+    fast_count: usize,
+    drill_count: usize,
+    tele_count: usize,
+    beacons: Vec<Point>,
+    collected: Vec<BoosterType>,
     robots: Vec<Robot>,
+    path_buffer: PathBuffer,
 }
 
 impl<'a> State<'a> {
-    fn initialize(task: &'a Task, buy: &Buy) -> State<'a> {
-        let map_points = task.map.enumerate_points();
-
+    fn initialize(
+        task: &'a Task,
+        buy: &Buy,
+        seed: u64,
+        strategy: &'a dyn Strategy,
+    ) -> State<'a> {
         let width = task.width;
         let height = task.height;
 
-        let mut remaining_pass = 0;
         let mut remaining_hand = 0;
         let mut remaining_clone = 0;
         let mut booster_map = Matrix::new(width, height, None);
-        let mut passed = Matrix::new(width, height, true);
-        let mut valid = Matrix::new(width, height, false);
-
-        for &p in &map_points {
-            passed.set(p, false);
-            valid.set(p, true);
-            remaining_pass += 1;
-        }
+        let valid = free_cells(task);
 
         for b in &task.boosters {
             booster_map.set(b.point, Some(b.kind.clone()));
@@ -147,31 +196,36 @@ impl<'a> State<'a> {
             remaining_clone = 0;
         }
 
-        for o in &task.obstacles {
-            for &p in o.enumerate_points().iter() {
-                if let Some(true) = valid.get(p) {
-                    valid.set(p, false);
-                    passed.set(p, true);
-                    remaining_pass -= 1;
-                }
-            }
-        }
+        // only free cells need to be wrapped
+        let remaining_pass = valid.count_ones();
+        let mut passed = BitMatrix::new(width, height, true);
+        passed.andnot(&valid);
 
         let turn = 0;
         let mut hand_count = 0;
         let mut clone_count = 0;
-        let robots = vec![Robot::initialize(task)];
+        let mut fast_count = 0;
+        let mut drill_count = 0;
+        let mut tele_count = 0;
+        let layout = strategy.layout(task);
+        let robots = vec![Robot::initialize(task, layout)];
 
         for b in buy.iter() {
             match b {
                 BoosterType::Cloning => clone_count += 1,
                 BoosterType::NewHand => hand_count += 1,
+                BoosterType::FastMove => fast_count += 1,
+                BoosterType::Drill => drill_count += 1,
+                BoosterType::Teleports => tele_count += 1,
                 _ => {}
             }
         }
 
         State {
             task,
+            strategy,
+            rng: StdRng::seed_from_u64(seed),
+            layout,
             turn,
             valid,
             passed,
@@ -181,10 +235,20 @@ impl<'a> State<'a> {
             remaining_pass,
             hand_count,
             clone_count,
+            fast_count,
+            drill_count,
+            tele_count,
+            beacons: Vec::new(),
+            collected: Vec::new(),
             robots,
+            path_buffer: PathBuffer::new(width, height),
         }
     }
 
+    fn is_finished(&self) -> bool {
+        self.remaining_pass == 0
+    }
+
     fn commands(&self) -> Commands {
         Commands::new(
             self.robots
@@ -195,87 +259,204 @@ impl<'a> State<'a> {
     }
 
     fn hand_reach(&self, place: Place, diff: Point) -> Option<Point> {
-        if diff.x.abs() > 1 || diff.y.abs() > 1 {
-            let d = std::cmp::max(diff.x.abs(), diff.y.abs());
-            assert!(diff.x.abs() == 0 || diff.x.abs() == d);
-            assert!(diff.y.abs() == 0 || diff.y.abs() == d);
-            let unit = Point::new(diff.x / d, diff.y / d);
-            for i in 1..=d {
-                let diff = Point::new(unit.x * i, unit.y * i);
-                let hand = place.hand(diff);
-                if let Some(true) = self.valid.get(hand) {
-                } else {
-                    return None;
-                }
-            }
+        let hand = place.hand(diff);
+        if is_visible(place.point(), hand, |p| self.valid.get(p) == Some(&true)) {
+            Some(hand)
+        } else {
+            None
         }
-        Some(place.hand(diff))
     }
 
-    fn is_goal(&self, robot_idx: usize, goal: Place) -> bool {
-        if self.remaining_clone > 0 {
-            return match self.booster_map.get(goal.point()) {
-                Some(Some(BoosterType::Cloning)) => true,
-                _ => false,
-            };
-        }
+    pub fn task(&self) -> &Task {
+        self.task
+    }
 
-        if self.clone_count > 0 {
-            return match self.booster_map.get(goal.point()) {
-                Some(Some(BoosterType::Spawn)) => true,
-                _ => false,
-            };
-        }
+    pub fn booster_at(&self, p: Point) -> Option<&BoosterType> {
+        self.booster_map.get(p).and_then(|b| b.as_ref())
+    }
 
-        if self.remaining_hand > 0 {
-            let first_robot_index = 0;
-            if robot_idx == first_robot_index {
-                return match self.booster_map.get(goal.point()) {
-                    Some(Some(BoosterType::NewHand)) => true,
-                    _ => false,
-                };
-            }
-        }
+    pub fn is_free(&self, p: Point) -> bool {
+        self.valid.get(p) == Some(&true)
+    }
 
+    pub fn is_passed(&self, p: Point) -> bool {
+        self.passed.get(p) == Some(&true)
+    }
 
-        let not_passed = self.robots[robot_idx]
+    pub fn beacons(&self) -> &[Point] {
+        &self.beacons
+    }
+
+    // boosters on the map and in the inventory not used or planned yet
+    pub fn remaining_hand(&self) -> usize {
+        self.remaining_hand
+    }
+
+    pub fn remaining_clone(&self) -> usize {
+        self.remaining_clone
+    }
+
+    pub fn clone_count(&self) -> usize {
+        self.clone_count
+    }
+
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    // free cells not wrapped yet
+    pub fn remaining(&self) -> usize {
+        self.remaining_pass
+    }
+
+    pub fn robot_count(&self) -> usize {
+        self.robots.len()
+    }
+
+    // absolute positions of the body and every manipulator, reachable or not
+    pub fn robot_bodies(&self, robot_idx: usize) -> Vec<Point> {
+        let robot = &self.robots[robot_idx];
+        robot
             .bodies_diff
             .iter()
-            .cloned()
-            .filter_map(|diff| self.hand_reach(goal, diff))
-            .any(|p| match self.passed.get(p) {
-                Some(false) => true,
-                _ => false,
-            });
+            .map(|&d| robot.current_place.hand(d))
+            .collect()
+    }
 
-        let is_booster = match self.booster_map.get(goal.point()) {
-            Some(Some(BoosterType::NewHand)) => true,
-            _ => false,
-        };
+    pub fn robot_place(&self, robot_idx: usize) -> Place {
+        self.robots[robot_idx].current_place
+    }
 
-        let is_valid = match self.valid.get(goal.point()) {
-            Some(true) => true,
-            _ => false,
-        };
+    pub fn robot_fast_remaining(&self, robot_idx: usize) -> usize {
+        self.robots[robot_idx].fast_remaining
+    }
 
-        is_valid && (not_passed || is_booster)
+    // unwrapped cells the robot would wrap at the place
+    pub fn count_pass(&self, robot_idx: usize, place: Place) -> usize {
+        match &self.robots[robot_idx].wrap_counts {
+            Some(counts) => counts[place.index(self.task.width)] as usize,
+            None => self.scan_pass(robot_idx, place),
+        }
     }
 
-    fn count_pass(&self, robot_idx: usize, place: Place) -> usize {
+    fn scan_pass(&self, robot_idx: usize, place: Place) -> usize {
         self.robots[robot_idx]
             .bodies_diff
             .iter()
             .cloned()
             .filter_map(|diff| self.hand_reach(place, diff))
-            .filter(|p| match self.passed.get(*p) {
-                Some(false) => true,
-                _ => false,
-            })
+            .filter(|p| matches!(self.passed.get(*p), Some(false)))
             .count()
     }
 
-    fn find_shortest_path(&self, robot_idx: usize, start: Place) -> Option<Vec<Move>> {
-        let mut rng = thread_rng();
+    fn ensure_wrap_counts(&mut self, robot_idx: usize) {
+        if self.robots[robot_idx].wrap_counts.is_some() {
+            return;
+        }
+        let width = self.task.width;
+        let mut counts = vec![0; width * self.task.height * 4];
+        // goals are never on blocked cells, drilled ones get counted when drilled
+        for y in 0..self.task.height {
+            for x in 0..width {
+                let p = Point::new(x as i32, y as i32);
+                if self.valid.get(p) != Some(&true) {
+                    continue;
+                }
+                for &dir in &DIRECTIONS {
+                    let place = Place::new(p, dir);
+                    counts[place.index(width)] = self.scan_pass(robot_idx, place) as u8;
+                }
+            }
+        }
+        self.robots[robot_idx].wrap_counts = Some(counts);
+    }
+
+    fn add_wrap_counts(&mut self, robot_idx: usize, diff: Point) {
+        let mut counts = match self.robots[robot_idx].wrap_counts.take() {
+            Some(counts) => counts,
+            None => return,
+        };
+        let width = self.task.width;
+        for y in 0..self.task.height {
+            for x in 0..width {
+                let p = Point::new(x as i32, y as i32);
+                if self.valid.get(p) != Some(&true) {
+                    continue;
+                }
+                for &dir in &DIRECTIONS {
+                    let place = Place::new(p, dir);
+                    let unpassed = self
+                        .hand_reach(place, diff)
+                        .is_some_and(|h| self.passed.get(h) == Some(&false));
+                    if unpassed {
+                        counts[place.index(width)] += 1;
+                    }
+                }
+            }
+        }
+        self.robots[robot_idx].wrap_counts = Some(counts);
+    }
+
+    // places of valid cells from which the given robot wraps `p`
+    fn places_wrapping(&self, robot_idx: usize, p: Point) -> Vec<Place> {
+        let mut res = Vec::new();
+        for &diff in &self.robots[robot_idx].bodies_diff {
+            for &dir in &DIRECTIONS {
+                let converted = dir.convert(diff);
+                let place = Place::new(Point::new(p.x - converted.x, p.y - converted.y), dir);
+                if self.valid.get(place.point()) == Some(&true)
+                    && self.hand_reach(place, diff) == Some(p)
+                {
+                    res.push(place);
+                }
+            }
+        }
+        res
+    }
+
+    // A drilled cell gets wrapped and can change the visibility of hands
+    // around it, so counts of every place within hand reach are recomputed.
+    fn refresh_wrap_counts(&mut self, p: Point) {
+        let width = self.task.width;
+        for robot_idx in 0..self.robots.len() {
+            if self.robots[robot_idx].wrap_counts.is_none() {
+                continue;
+            }
+            let reach = self.robots[robot_idx]
+                .bodies_diff
+                .iter()
+                .map(|d| std::cmp::max(d.x.abs(), d.y.abs()))
+                .max()
+                .unwrap_or(0);
+            let mut updates = Vec::new();
+            for y in p.y - reach..=p.y + reach {
+                for x in p.x - reach..=p.x + reach {
+                    let q = Point::new(x, y);
+                    if self.valid.get(q) != Some(&true) {
+                        continue;
+                    }
+                    for &dir in &DIRECTIONS {
+                        let place = Place::new(q, dir);
+                        updates.push((place.index(width), self.scan_pass(robot_idx, place)));
+                    }
+                }
+            }
+            let counts = self.robots[robot_idx].wrap_counts.as_mut().unwrap();
+            for (idx, count) in updates {
+                counts[idx] = count as u8;
+            }
+        }
+    }
+
+    fn find_shortest_path(
+        &mut self,
+        robot_idx: usize,
+        start: Place,
+        fast_remaining: usize,
+        drill_remaining: usize,
+        jump_first: bool,
+        max_len: Option<usize>,
+    ) -> Option<Vec<Command>> {
         let mut moves = [
             Move::MoveUp,
             Move::MoveDown,
@@ -285,22 +466,28 @@ impl<'a> State<'a> {
             Move::TurnRight,
         ];
 
-        let mut data: HashMap<Place, (Move, u32)> = HashMap::new();
-        let mut queue = VecDeque::new();
-        queue.push_back(start);
-        data.insert(start, (Move::Noop, 0));
+        let fast_remaining = fast_remaining as u32;
+        let drill_remaining = drill_remaining as u32;
+        let width = self.task.width;
+        self.ensure_wrap_counts(robot_idx);
+
+        // taken out so that the search can borrow self while filling it
+        let mut buf = std::mem::take(&mut self.path_buffer);
+        buf.clear();
+        buf.queue.push_back(start);
+        buf.visit(start.index(width), start, Command::Move(Move::Noop), 0);
 
         let mut goal = None;
         let mut goal_value = None;
 
-        while let Some(place) = queue.pop_front() {
-            let (_, cost) = data[&place];
+        while let Some(place) = buf.queue.pop_front() {
+            let cost = buf.costs[place.index(width)];
 
-            if self.is_goal(robot_idx, place) {
+            if self.strategy.is_goal(self, robot_idx, place) {
                 let value = (
-                    u32::max_value() - cost,
-                    self.count_pass(robot_idx, place),
-                    rng.gen::<usize>(),
+                    u32::MAX - cost,
+                    self.strategy.goal_value(self, robot_idx, place),
+                    self.rng.gen::<usize>(),
                 );
                 match goal_value {
                     Some(goal_value) if goal_value > value => {}
@@ -315,31 +502,87 @@ impl<'a> State<'a> {
                 continue;
             }
 
-            moves.shuffle(&mut rng);
+            if let Some(max_len) = max_len {
+                if cost as usize >= max_len {
+                    continue;
+                }
+            }
+
+            // jumping later is never cheaper than jumping right away, and walking
+            // paths are covered by the search without beacons
+            if jump_first && place == start {
+                for &b in &self.beacons {
+                    let nplace = Place::new(b, place.dir());
+                    let idx = nplace.index(width);
+                    if !buf.is_visited(idx) {
+                        buf.visit(idx, place, Command::ShiftBeacon(b), cost + 1);
+                        buf.queue.push_back(nplace);
+                    }
+                }
+                continue;
+            }
+
+            moves.shuffle(&mut self.rng);
+            let fast = cost < fast_remaining;
+            let drill = cost < drill_remaining;
             for m in &moves {
-                let nplace = place.move_with(m);
-                if let Some(true) = self.valid.get(nplace.point()) {
-                    data.entry(nplace).or_insert_with(|| {
-                        queue.push_back(nplace);
-                        (m.clone(), cost + 1)
-                    });
+                if let Some(nplace) = self.step_place(place, m, fast, drill) {
+                    let idx = nplace.index(width);
+                    if !buf.is_visited(idx) {
+                        buf.visit(idx, place, Command::Move(m.clone()), cost + 1);
+                        buf.queue.push_back(nplace);
+                    }
                 }
             }
         }
 
-        if let Some(goal) = goal {
+        let res = goal.map(|goal| {
             let mut res = Vec::new();
             let mut iter = goal;
             while iter != start {
-                let (mv, _) = &data[&iter];
-                iter = iter.revert_with(mv);
-                res.push(mv.clone());
+                let (prev, cmd) = &buf.parents[iter.index(width)];
+                iter = *prev;
+                res.push(cmd.clone());
             }
             res.reverse();
-            return Some(res);
+            res
+        });
+        self.path_buffer = buf;
+        res
+    }
+
+    // a drill can go through any cell inside the map bounds
+    fn can_enter(&self, p: Point, drill: bool) -> bool {
+        match self.valid.get(p) {
+            Some(true) => true,
+            Some(false) => drill,
+            None => false,
         }
+    }
 
-        None
+    // With fast wheels a move goes two cells, or one if the second is blocked.
+    fn step_place(&self, place: Place, m: &Move, fast: bool, drill: bool) -> Option<Place> {
+        let nplace = place.move_with(m);
+        if !self.can_enter(nplace.point(), drill) {
+            return None;
+        }
+        if fast && nplace.point() != place.point() {
+            let fplace = nplace.move_with(m);
+            if self.can_enter(fplace.point(), drill) {
+                return Some(fplace);
+            }
+        }
+        Some(nplace)
+    }
+
+    // drilled cells become free space, already wrapped by the robot on them
+    fn drill_current_point(&mut self, robot_idx: usize) {
+        let p = self.robots[robot_idx].current_place.point();
+        if let Some(false) = self.valid.get(p) {
+            self.valid.set(p, true);
+            self.passed.set(p, true);
+            self.refresh_wrap_counts(p);
+        }
     }
 
     fn pass_current_point(&mut self, robot_idx: usize) {
@@ -348,42 +591,59 @@ impl<'a> State<'a> {
             .iter()
             .filter_map(|diff| self.hand_reach(self.robots[robot_idx].current_place, *diff))
             .collect::<Vec<_>>();
+        let width = self.task.width;
         for b in bodies {
             if let Some(false) = self.passed.get(b) {
                 self.passed.set(b, true);
                 self.remaining_pass -= 1;
+                for idx in 0..self.robots.len() {
+                    if self.robots[idx].wrap_counts.is_none() {
+                        continue;
+                    }
+                    let places = self.places_wrapping(idx, b);
+                    let counts = self.robots[idx].wrap_counts.as_mut().unwrap();
+                    for place in places {
+                        counts[place.index(width)] -= 1;
+                    }
+                }
             }
         }
     }
 
     pub fn get_booster(&mut self, robot_idx: usize) {
         let current_point = self.robots[robot_idx].current_place.point();
-        if let Some(Some(kind)) = self.booster_map.get(current_point) {
-            match kind {
-                BoosterType::NewHand => {
-                    self.remaining_hand -= 1;
-                    self.hand_count += 1;
-                    self.booster_map.set(current_point, None);
-                }
-                BoosterType::Teleports => {
-                    self.booster_map.set(current_point, None);
-                }
-                BoosterType::Drill => {
-                    self.booster_map.set(current_point, None);
-                }
-                BoosterType::Cloning => {
-                    self.remaining_clone -= 1;
-                    self.clone_count += 1;
-                    self.booster_map.set(current_point, None);
-                }
-                BoosterType::Spawn => {}
-                BoosterType::FastMove => {
-                    self.booster_map.set(current_point, None);
+        if let Some(kind) = self.take_booster(current_point) {
+            self.store_booster(&kind);
+        }
+    }
+
+    fn take_booster(&mut self, p: Point) -> Option<BoosterType> {
+        match self.booster_map.get(p) {
+            Some(Some(BoosterType::Spawn)) | Some(None) | None => None,
+            Some(Some(kind)) => {
+                let kind = kind.clone();
+                match kind {
+                    BoosterType::NewHand => self.remaining_hand -= 1,
+                    BoosterType::Cloning => self.remaining_clone -= 1,
+                    _ => {}
                 }
+                self.booster_map.set(p, None);
+                Some(kind)
             }
         }
     }
 
+    fn store_booster(&mut self, kind: &BoosterType) {
+        match kind {
+            BoosterType::NewHand => self.hand_count += 1,
+            BoosterType::Cloning => self.clone_count += 1,
+            BoosterType::FastMove => self.fast_count += 1,
+            BoosterType::Drill => self.drill_count += 1,
+            BoosterType::Teleports => self.tele_count += 1,
+            _ => {}
+        }
+    }
+
     pub fn fill_next_command(&mut self, robot_idx: usize) {
         assert!(self.turn <= self.robots[robot_idx].commands.len());
         let current_place = self.robots[robot_idx].current_place;
@@ -418,18 +678,95 @@ impl<'a> State<'a> {
             }
         }
 
+        if self.fast_count > 0 && self.strategy.use_fast_wheel(self, robot_idx) {
+            let robot = &mut self.robots[robot_idx];
+            self.fast_count -= 1;
+            robot.commands.insert(self.turn, Command::FastWheel);
+            robot.commands.truncate(self.turn + 1);
+            return;
+        }
+
+        if self.tele_count > 0 && self.strategy.install_beacon(self, robot_idx) {
+            let robot = &mut self.robots[robot_idx];
+            self.tele_count -= 1;
+            robot.commands.insert(self.turn, Command::ResetBeacon);
+            robot.commands.truncate(self.turn + 1);
+            return;
+        }
+
         if self.turn < self.robots[robot_idx].commands.len() {
             return;
         }
 
-        let base_moves = self.find_shortest_path(robot_idx, current_place);
-        if let Some(base_moves) = base_moves {
-            for m in &base_moves {
-                self.robots[robot_idx]
-                    .commands
-                    .push(Command::Move(m.clone()));
+        let fast_remaining = self.robots[robot_idx].fast_remaining;
+        let drill_remaining = self.robots[robot_idx].drill_remaining;
+        let base_moves = self.find_shortest_path(
+            robot_idx,
+            current_place,
+            fast_remaining,
+            drill_remaining,
+            false,
+            None,
+        );
+
+        if self.drill_count > 0 && drill_remaining == 0 {
+            // plan as if the drill is activated in this turn
+            let drill_moves = self.find_shortest_path(
+                robot_idx,
+                current_place,
+                fast_remaining.saturating_sub(1),
+                DRILL_TURNS - 1,
+                false,
+                None,
+            );
+            let worth_drilling = match &drill_moves {
+                Some(drill) => self
+                    .strategy
+                    .use_drill(base_moves.as_ref().map(|b| b.len()), drill.len()),
+                None => false,
+            };
+            if worth_drilling {
+                self.drill_count -= 1;
+                let mut drill_moves = drill_moves.unwrap();
+                // drilled cells change how later moves end, so replan once it expires
+                drill_moves.truncate(DRILL_TURNS - 1);
+                let robot = &mut self.robots[robot_idx];
+                robot.commands.push(Command::Drill);
+                robot.commands.extend(drill_moves);
+                return;
+            }
+        }
+
+        let max_len = if self.beacons.is_empty() {
+            None
+        } else {
+            self.strategy
+                .jump_limit(base_moves.as_ref().map(|b| b.len()))
+        };
+        if let Some(max_len) = max_len {
+            let beacon_moves = self.find_shortest_path(
+                robot_idx,
+                current_place,
+                fast_remaining,
+                drill_remaining,
+                true,
+                Some(max_len),
+            );
+            if let Some(mut beacon_moves) = beacon_moves {
+                if drill_remaining > 0 {
+                    beacon_moves.truncate(drill_remaining);
+                }
+                self.robots[robot_idx].commands.extend(beacon_moves);
+                return;
+            }
+        }
+
+        if let Some(mut base_moves) = base_moves {
+            if drill_remaining > 0 {
+                base_moves.truncate(drill_remaining);
             }
             if !base_moves.is_empty() {
+                self.robots[robot_idx].commands.extend(base_moves);
                 return;
             }
         }
@@ -439,6 +776,18 @@ impl<'a> State<'a> {
             .push(Command::Move(Move::Noop));
     }
 
+    fn is_executable(&self, robot_idx: usize) -> bool {
+        let robot = &self.robots[robot_idx];
+        match &robot.commands[self.turn] {
+            Command::Move(m) => {
+                let fast = robot.fast_remaining > 0;
+                let drill = robot.drill_remaining > 0;
+                self.step_place(robot.current_place, m, fast, drill).is_some()
+            }
+            _ => true,
+        }
+    }
+
     // true if it continues
     pub fn next_state(&mut self) -> bool {
         if self.remaining_pass == 0 {
@@ -448,7 +797,10 @@ impl<'a> State<'a> {
         let turn = self.turn;
         let robots_len = self.robots.len();
         for idx in 0..robots_len {
-            self.pass_current_point(idx);
+            // as in the simulator, a clone wraps nothing before its first command
+            if idx == 0 || !self.robots[idx].executed.is_empty() {
+                self.pass_current_point(idx);
+            }
             self.get_booster(idx);
 
             if self.remaining_pass == 0 {
@@ -456,27 +808,70 @@ impl<'a> State<'a> {
             }
 
             self.fill_next_command(idx);
+            if !self.is_executable(idx) {
+                // cells drilled since planning can change where moves end up
+                self.robots[idx].commands.truncate(turn);
+                self.fill_next_command(idx);
+            }
 
             assert!(turn < self.robots[idx].commands.len());
             let m = self.robots[idx].commands[turn].clone();
             match &m {
                 Command::Move(m) => {
-                    self.robots[idx].move_with(&m);
+                    let fast = self.robots[idx].fast_remaining > 0;
+                    let drill = self.robots[idx].drill_remaining > 0;
+                    let current_place = self.robots[idx].current_place;
+                    let next_place = self.step_place(current_place, m, fast, drill).unwrap();
+                    self.robots[idx].move_with(m);
+                    self.drill_current_point(idx);
+                    if next_place != self.robots[idx].current_place {
+                        // the cell passed by fast wheels is wrapped and its booster is
+                        // usable from the next turn
+                        self.pass_current_point(idx);
+                        let passed_point = self.robots[idx].current_place.point();
+                        if let Some(kind) = self.take_booster(passed_point) {
+                            self.collected.push(kind);
+                        }
+                        self.robots[idx].move_with(m);
+                        self.drill_current_point(idx);
+                    }
+                }
+                Command::FastWheel => {
+                    self.robots[idx].fast_remaining = FAST_WHEEL_TURNS;
+                }
+                Command::Drill => {
+                    self.robots[idx].drill_remaining = DRILL_TURNS;
+                }
+                Command::ResetBeacon => {
+                    let p = self.robots[idx].current_place.point();
+                    self.beacons.push(p);
+                }
+                Command::ShiftBeacon(p) => {
+                    let dir = self.robots[idx].current_place.dir();
+                    self.robots[idx].current_place = Place::new(*p, dir);
+                    // a booster under the beacon is usable from the next turn
+                    if let Some(kind) = self.take_booster(*p) {
+                        self.collected.push(kind);
+                    }
                 }
                 Command::NewHand(ref p) => {
                     let p = self.robots[idx].current_place.dir().reconvert(*p);
                     self.robots[idx].bodies_diff.push(p);
+                    self.add_wrap_counts(idx, p);
                 }
                 Command::Cloning => {
-                    let new_robot = Robot::clone_from(&self.robots[idx]);
+                    let new_robot = Robot::clone_from(&self.robots[idx], self.layout);
                     assert!(new_robot.commands.len() == self.turn + 1);
                     self.robots.push(new_robot);
                 }
-                _ => unreachable!(),
             }
             self.robots[idx].executed.push(m);
+            self.robots[idx].consume_turn();
             self.pass_current_point(idx);
         }
+        for kind in std::mem::take(&mut self.collected) {
+            self.store_booster(&kind);
+        }
         self.turn += 1;
         assert!(self.turn < 1_000_000_000);
 
@@ -484,29 +879,271 @@ impl<'a> State<'a> {
     }
 }
 
-pub fn solve_small_while(task: Task, buy: &Buy, duration: Duration) -> Commands {
-    let mut res = solve_small(task.clone(), buy);
-    let now = Instant::now();;
-    loop {
-        if now.elapsed() >= duration {
-            break;
+#[derive(Debug, Clone)]
+pub struct SolveStats {
+    pub strategy: &'static str,
+    pub layout: LayoutKind,
+    pub turns: usize,
+    pub restarts: usize,
+    // restarts given up once they could no longer beat the best run
+    pub aborted: usize,
+    // seed of the run which produced the returned commands
+    pub seed: u64,
+    // cells wrapped by the end of each turn of that run
+    pub wrapped: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution {
+    pub commands: Commands,
+    pub stats: SolveStats,
+}
+
+// A finished run of the solver.
+struct Run {
+    commands: Commands,
+    wrapped: Vec<usize>,
+}
+
+// Restarts shared by the workers of a single task.
+struct Search {
+    seeds: StdRng,
+    next_seed: Option<u64>,
+    runs: usize,
+    aborted: usize,
+    best: Option<(Run, u64)>,
+}
+
+impl Search {
+    fn new(seed: u64) -> Search {
+        Search {
+            seeds: StdRng::seed_from_u64(seed),
+            next_seed: Some(seed),
+            runs: 0,
+            aborted: 0,
+            best: None,
         }
-        let new = solve_small(task.clone(), buy);
-        if new.len() < res.len() {
-            res = new;
+    }
+
+    fn take_seed(&mut self) -> u64 {
+        self.runs += 1;
+        match self.next_seed.take() {
+            Some(seed) => seed,
+            None => self.seeds.gen::<u64>(),
+        }
+    }
+
+    fn update(&mut self, run: Run, seed: u64) {
+        let better = match &self.best {
+            Some((best, best_seed)) => {
+                (run.commands.len(), seed) < (best.commands.len(), *best_seed)
+            }
+            None => true,
+        };
+        if better {
+            self.best = Some((run, seed));
         }
     }
-    res
 }
 
-pub fn solve_small(task: Task, buy: &Buy) -> Commands {
-    let mut state = State::initialize(&task, buy);
+/// Solves a single task. Defaults to a single run of the default strategy with
+/// seed 0 and no boosters bought.
+///
+/// ```
+/// use icfpc::parse::{read_buy, read_task};
+/// use icfpc::sim::simulate;
+/// use icfpc::solve::Solver;
+/// use std::time::Duration;
+///
+/// let task = read_task("(0,0),(10,0),(10,10),(0,10)#(0,0)##", "example".to_owned()).unwrap();
+/// let buy = read_buy("B").unwrap();
+/// let solution = Solver::new(task.clone())
+///     .buy(buy.clone())
+///     .seed(42)
+///     .time_budget(Duration::from_millis(10))
+///     .solve();
+/// assert_eq!(simulate(&task, &buy, &solution.commands), Ok(solution.stats.turns));
+/// ```
+pub struct Solver<'a> {
+    task: Task,
+    buy: Buy,
+    seed: u64,
+    time_budget: Duration,
+    strategy: &'a dyn Strategy,
+    watch: Option<(usize, &'a Watcher<'a>)>,
+}
+
+/// Called with the state of a run in progress.
+pub type Watcher<'a> = dyn Fn(&State) + Sync + 'a;
+
+impl<'a> Solver<'a> {
+    pub fn new(task: Task) -> Solver<'a> {
+        Solver {
+            task,
+            buy: Buy::new(),
+            seed: 0,
+            time_budget: Duration::from_secs(0),
+            strategy: default_strategy(),
+            watch: None,
+        }
+    }
+
+    pub fn buy(mut self, buy: Buy) -> Solver<'a> {
+        self.buy = buy;
+        self
+    }
+
+    /// The first run uses `seed` itself and restarts draw their seeds from it.
+    pub fn seed(mut self, seed: u64) -> Solver<'a> {
+        self.seed = seed;
+        self
+    }
+
+    /// Restarts run on the rayon pool until the budget is used up. The first
+    /// run always completes.
+    pub fn time_budget(mut self, time_budget: Duration) -> Solver<'a> {
+        self.time_budget = time_budget;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: &'a dyn Strategy) -> Solver<'a> {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Shows the first run every `turns` turns and once it finishes.
+    pub fn watch(mut self, turns: usize, watcher: &'a Watcher<'a>) -> Solver<'a> {
+        assert!(turns > 0);
+        self.watch = Some((turns, watcher));
+        self
+    }
+
+    pub fn solve(&self) -> Solution {
+        let search = Mutex::new(Search::new(self.seed));
+        let now = Instant::now();
+        (0..rayon::current_num_threads())
+            .into_par_iter()
+            .for_each(|_| loop {
+                let (seed, max_turns, watch) = {
+                    let mut search = search.lock().unwrap();
+                    if search.runs > 0 && now.elapsed() >= self.time_budget {
+                        break;
+                    }
+                    let max_turns = search.best.as_ref().map(|(b, _)| b.commands.len());
+                    let watch = if search.runs == 0 { self.watch } else { None };
+                    (search.take_seed(), max_turns, watch)
+                };
+                // runs before the first solution is known have to complete
+                let deadline = max_turns.map(|_| now + self.time_budget);
+                let run = run_once(
+                    &self.task,
+                    &self.buy,
+                    seed,
+                    self.strategy,
+                    max_turns,
+                    deadline,
+                    watch,
+                );
+                let mut search = search.lock().unwrap();
+                match run {
+                    Some(run) => search.update(run, seed),
+                    None => search.aborted += 1,
+                }
+            });
+
+        let search = search.into_inner().unwrap();
+        let (run, best_seed) = search.best.unwrap();
+        let stats = SolveStats {
+            strategy: self.strategy.name(),
+            layout: self.strategy.layout(&self.task),
+            turns: run.commands.len(),
+            restarts: search.runs - 1,
+            aborted: search.aborted,
+            seed: best_seed,
+            wrapped: run.wrapped,
+        };
+        Solution {
+            commands: run.commands,
+            stats,
+        }
+    }
+}
+
+pub fn solve_small_while(
+    task: Task,
+    buy: &Buy,
+    duration: Duration,
+    seed: u64,
+    strategy: &dyn Strategy,
+) -> (Commands, SolveStats) {
+    let solution = Solver::new(task)
+        .buy(buy.clone())
+        .seed(seed)
+        .time_budget(duration)
+        .strategy(strategy)
+        .solve();
+    (solution.commands, solution.stats)
+}
+
+pub fn solve_small(task: Task, buy: &Buy, seed: u64, strategy: &dyn Strategy) -> Commands {
+    solve_small_bounded(task, buy, seed, strategy, None, None).unwrap()
+}
+
+// Gives up and returns None once the run would take `max_turns` turns or more,
+// or when `deadline` passes.
+pub fn solve_small_bounded(
+    task: Task,
+    buy: &Buy,
+    seed: u64,
+    strategy: &dyn Strategy,
+    max_turns: Option<usize>,
+    deadline: Option<Instant>,
+) -> Option<Commands> {
+    run_once(&task, buy, seed, strategy, max_turns, deadline, None).map(|run| run.commands)
+}
+
+fn run_once(
+    task: &Task,
+    buy: &Buy,
+    seed: u64,
+    strategy: &dyn Strategy,
+    max_turns: Option<usize>,
+    deadline: Option<Instant>,
+    watch: Option<(usize, &Watcher<'_>)>,
+) -> Option<Run> {
+    let mut state = State::initialize(task, buy, seed, strategy);
+    let area = state.remaining_pass;
+    let mut wrapped = Vec::new();
     loop {
-        if !state.next_state() {
+        let running = state.next_state();
+        wrapped.push(area - state.remaining_pass);
+        if let Some((turns, watcher)) = watch {
+            if !running || state.turn.is_multiple_of(turns) {
+                watcher(&state);
+            }
+        }
+        if !running {
             break;
         }
+        if state.is_finished() {
+            continue;
+        }
+        // the next turn is already the one the result would take to finish
+        if let Some(max_turns) = max_turns {
+            if state.turn + 1 >= max_turns {
+                return None;
+            }
+        }
+        if let Some(deadline) = deadline {
+            if Instant::now() >= deadline {
+                return None;
+            }
+        }
     }
-    state.commands()
+    Some(Run {
+        commands: state.commands(),
+        wrapped,
+    })
 }
 
 pub fn determine_buy(task: &Task) -> Buy {
@@ -537,4 +1174,99 @@ pub fn determine_buy(task: &Task) -> Buy {
         }
     }
     buy
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{read_buy, read_task};
+    use crate::sim::simulate;
+    use crate::strategy::find_strategy;
+
+    fn task(s: &str) -> Task {
+        read_task(s, "test".to_owned()).unwrap()
+    }
+
+    // solves with a single run and checks the result with the simulator
+    fn solve_checked(task: &Task, buy: &str, strategy: &str) -> Commands {
+        let buy = read_buy(buy).unwrap();
+        let commands = solve_small(task.clone(), &buy, 0, find_strategy(strategy).unwrap());
+        assert_eq!(simulate(task, &buy, &commands), Ok(commands.len()));
+        commands
+    }
+
+    // runs the solver once and keeps the final state for inspection
+    fn run_state<'a>(task: &'a Task, buy: &Buy, strategy: &str) -> State<'a> {
+        let mut state = State::initialize(task, buy, 0, find_strategy(strategy).unwrap());
+        while state.next_state() {}
+        let commands = state.commands();
+        assert_eq!(simulate(task, buy, &commands), Ok(commands.len()));
+        state
+    }
+
+    #[test]
+    fn clone_wraps_after_its_first_command() {
+        // the clone faces right at the spawn point, unlike its parent, and
+        // must not count the cells its hands cover there as wrapped
+        let task = task("(0,0),(5,0),(5,7),(0,7)#(0,0)##X(1,3)");
+        let commands = solve_checked(&task, "C", "wrap-only");
+        assert_eq!(commands.robot_count(), 2);
+    }
+
+    #[test]
+    fn fast_wheel_in_a_corridor() {
+        // the hands wrap the next cell, so the robot stops two cells short of
+        // the end after 14 double moves
+        let task = task("(0,0),(30,0),(30,1),(0,1)#(0,0)##");
+        let commands = solve_checked(&task, "F", "greedy");
+        assert_eq!(commands.get(0).unwrap()[0], Command::FastWheel);
+        assert_eq!(commands.len(), 1 + 14);
+    }
+
+    #[test]
+    fn fast_wheel_blocked_second_step() {
+        // the hands reach nothing in a vertical corridor, so the robot has to
+        // enter the last cell with a move whose second step is blocked
+        let task = task("(0,0),(1,0),(1,30),(0,30)#(0,0)##");
+        let commands = solve_checked(&task, "F", "greedy");
+        assert_eq!(commands.get(0).unwrap()[0], Command::FastWheel);
+        assert_eq!(commands.len(), 1 + 14 + 1);
+    }
+
+    #[test]
+    fn drill_through_a_thin_wall() {
+        // the left strip is wrapped top down, after which the right room is
+        // a couple of cells away through the wall but over twenty around it
+        let task = task("(0,0),(3,0),(3,21),(4,21),(4,0),(14,0),(14,22),(0,22)#(1,20)##");
+        let walked = run_state(&task, &Buy::new(), "greedy").commands().len();
+        let buy = read_buy("L").unwrap();
+        let state = run_state(&task, &buy, "greedy");
+        let commands = state.commands();
+        assert!(commands.get(0).unwrap().contains(&Command::Drill));
+        assert!(commands.len() < walked);
+
+        let drilled = (0..20)
+            .map(|y| Point::new(3, y))
+            .filter(|&p| state.is_free(p))
+            .collect::<Vec<_>>();
+        assert!(!drilled.is_empty());
+        assert!(drilled.iter().all(|&p| state.is_passed(p)));
+    }
+
+    #[test]
+    fn beacons_on_a_large_map() {
+        // the start looks like a good hub but is a spawn point
+        let task = task("(0,0),(60,0),(60,60),(0,60)#(0,0)##X(0,0);X(30,30)");
+        let buy = read_buy("RR").unwrap();
+        let state = run_state(&task, &buy, "greedy");
+        let commands = state.commands();
+        let issued = |f: &dyn Fn(&Command) -> bool| commands.iter().flatten().any(f);
+        assert!(issued(&|c| *c == Command::ResetBeacon));
+        assert!(issued(&|c| matches!(c, Command::ShiftBeacon(_))));
+        assert!(!state.beacons().is_empty());
+        assert!(state
+            .beacons()
+            .iter()
+            .all(|&b| state.booster_at(b) != Some(&BoosterType::Spawn)));
+    }
+}
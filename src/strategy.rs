@@ -0,0 +1,227 @@
+use crate::layout::{choose_layout, LayoutKind};
+use crate::models::*;
+use crate::solve::State;
+
+// turns a drilled path has to save to be worth using a drill
+const DRILL_MIN_GAIN: usize = 5;
+// minimum manhattan distance between two beacons
+const BEACON_MIN_SPACING: i32 = 10;
+// walks shorter than this are never replaced by a jump to a beacon
+const BEACON_MIN_WALK: usize = 20;
+
+pub const DEFAULT_STRATEGY: &str = "greedy";
+
+// Planning decisions of the solver. The state does the path finding and keeps
+// the plan, a strategy decides where to go and which boosters to use.
+pub trait Strategy: Sync {
+    fn name(&self) -> &'static str;
+
+    // order in which new manipulators get attached
+    fn layout(&self, task: &Task) -> LayoutKind;
+
+    fn is_goal(&self, state: &State, robot_idx: usize, place: Place) -> bool;
+
+    // breaks ties between goals at the same distance, higher is better
+    fn goal_value(&self, state: &State, robot_idx: usize, place: Place) -> usize;
+
+    fn use_fast_wheel(&self, state: &State, robot_idx: usize) -> bool;
+
+    // lengths of the best paths without and with a drill activated now
+    fn use_drill(&self, base_len: Option<usize>, drill_len: usize) -> bool;
+
+    fn install_beacon(&self, state: &State, robot_idx: usize) -> bool;
+
+    // Bound on the length of a path jumping to a beacon given the walking one,
+    // or None to walk.
+    fn jump_limit(&self, base_len: Option<usize>) -> Option<usize>;
+}
+
+// Wraps the nearest cells, collects every useful booster and uses them as
+// soon as possible. The layout is chosen per task unless fixed, which allows
+// comparing layouts with the same planning.
+pub struct Greedy {
+    name: &'static str,
+    layout: Option<LayoutKind>,
+}
+
+impl Strategy for Greedy {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn layout(&self, task: &Task) -> LayoutKind {
+        self.layout.unwrap_or_else(|| choose_layout(task))
+    }
+
+    fn is_goal(&self, state: &State, robot_idx: usize, goal: Place) -> bool {
+        let booster = state.booster_at(goal.point());
+        if state.remaining_clone() > 0 {
+            return booster == Some(&BoosterType::Cloning);
+        }
+
+        if state.clone_count() > 0 {
+            return booster == Some(&BoosterType::Spawn);
+        }
+
+        if state.remaining_hand() > 0 {
+            let first_robot_index = 0;
+            if robot_idx == first_robot_index {
+                return booster == Some(&BoosterType::NewHand);
+            }
+        }
+
+        let not_passed = state.count_pass(robot_idx, goal) > 0;
+
+        let is_booster = matches!(
+            booster,
+            Some(BoosterType::NewHand)
+                | Some(BoosterType::FastMove)
+                | Some(BoosterType::Drill)
+                | Some(BoosterType::Teleports)
+        );
+
+        state.is_free(goal.point()) && (not_passed || is_booster)
+    }
+
+    fn goal_value(&self, state: &State, robot_idx: usize, place: Place) -> usize {
+        state.count_pass(robot_idx, place)
+    }
+
+    fn use_fast_wheel(&self, state: &State, robot_idx: usize) -> bool {
+        state.robot_fast_remaining(robot_idx) == 0
+    }
+
+    fn use_drill(&self, base_len: Option<usize>, drill_len: usize) -> bool {
+        match base_len {
+            Some(base_len) => drill_len + 1 + DRILL_MIN_GAIN <= base_len,
+            None => true,
+        }
+    }
+
+    fn install_beacon(&self, state: &State, robot_idx: usize) -> bool {
+        is_beacon_hub(state, state.robot_place(robot_idx).point())
+    }
+
+    // a jump has to at least halve a long walk, which also bounds the search
+    fn jump_limit(&self, base_len: Option<usize>) -> Option<usize> {
+        match base_len {
+            Some(base_len) if base_len < BEACON_MIN_WALK => None,
+            Some(base_len) => Some(base_len / 2),
+            None => Some(usize::MAX),
+        }
+    }
+}
+
+// Wraps the nearest cells with the manipulators attached along the way and
+// leaves every other booster alone. Mostly useful as a baseline.
+pub struct WrapOnly;
+
+impl Strategy for WrapOnly {
+    fn name(&self) -> &'static str {
+        "wrap-only"
+    }
+
+    fn layout(&self, task: &Task) -> LayoutKind {
+        GREEDY.layout(task)
+    }
+
+    fn is_goal(&self, state: &State, robot_idx: usize, goal: Place) -> bool {
+        if state.remaining_hand() > 0 && robot_idx == 0 {
+            return state.booster_at(goal.point()) == Some(&BoosterType::NewHand);
+        }
+        state.is_free(goal.point()) && state.count_pass(robot_idx, goal) > 0
+    }
+
+    fn goal_value(&self, state: &State, robot_idx: usize, place: Place) -> usize {
+        GREEDY.goal_value(state, robot_idx, place)
+    }
+
+    fn use_fast_wheel(&self, _state: &State, _robot_idx: usize) -> bool {
+        false
+    }
+
+    fn use_drill(&self, _base_len: Option<usize>, _drill_len: usize) -> bool {
+        false
+    }
+
+    fn install_beacon(&self, _state: &State, _robot_idx: usize) -> bool {
+        false
+    }
+
+    fn jump_limit(&self, _base_len: Option<usize>) -> Option<usize> {
+        None
+    }
+}
+
+// A beacon goes in the middle of a mostly unwrapped region away from other beacons,
+// so that robots can jump back instead of walking there.
+fn is_beacon_hub(state: &State, p: Point) -> bool {
+    // beacons cannot be installed on spawn points
+    if state.booster_at(p) == Some(&BoosterType::Spawn) {
+        return false;
+    }
+    let task = state.task();
+    let spacing = std::cmp::max(
+        std::cmp::max(task.width, task.height) as i32 / 4,
+        BEACON_MIN_SPACING,
+    );
+    let near_beacon = state
+        .beacons()
+        .iter()
+        .any(|b| (b.x - p.x).abs() + (b.y - p.y).abs() < spacing);
+    if near_beacon {
+        return false;
+    }
+    let radius = spacing / 2;
+    let stride = std::cmp::max(radius / 8, 1) as usize;
+    let mut valid = 0;
+    let mut not_passed = 0;
+    for x in (p.x - radius..=p.x + radius).step_by(stride) {
+        for y in (p.y - radius..=p.y + radius).step_by(stride) {
+            let q = Point::new(x, y);
+            if state.is_free(q) {
+                valid += 1;
+                if !state.is_passed(q) {
+                    not_passed += 1;
+                }
+            }
+        }
+    }
+    not_passed * 2 >= valid
+}
+
+static GREEDY: Greedy = Greedy {
+    name: "greedy",
+    layout: None,
+};
+static GREEDY_LINE: Greedy = Greedy {
+    name: "greedy-line",
+    layout: Some(LayoutKind::Line),
+};
+static GREEDY_WIDE_T: Greedy = Greedy {
+    name: "greedy-wide-t",
+    layout: Some(LayoutKind::WideT),
+};
+static GREEDY_STAGGERED: Greedy = Greedy {
+    name: "greedy-staggered",
+    layout: Some(LayoutKind::Staggered),
+};
+static WRAP_ONLY: WrapOnly = WrapOnly;
+
+pub fn strategies() -> Vec<&'static dyn Strategy> {
+    vec![
+        &GREEDY,
+        &GREEDY_LINE,
+        &GREEDY_WIDE_T,
+        &GREEDY_STAGGERED,
+        &WRAP_ONLY,
+    ]
+}
+
+pub fn find_strategy(name: &str) -> Option<&'static dyn Strategy> {
+    strategies().into_iter().find(|s| s.name() == name)
+}
+
+pub fn default_strategy() -> &'static dyn Strategy {
+    &GREEDY
+}
@@ -1,4 +1,5 @@
-use crate::models::Point;
+use crate::models::{Map, Point, Task};
+use std::cmp;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Matrix<T> {
@@ -50,6 +51,222 @@ impl<T: Clone> Matrix<T> {
     }
 }
 
+// Boolean grid packed into 64-bit words, row by row. `get` hands out references
+// to static bools so call sites read the same as with `Matrix<bool>`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BitMatrix {
+    width: usize,
+    height: usize,
+    inner: Vec<u64>,
+}
+
+impl BitMatrix {
+    pub fn new(width: usize, height: usize, init: bool) -> BitMatrix {
+        let n = width * height;
+        let word = if init { !0 } else { 0 };
+        let mut res = BitMatrix {
+            width,
+            height,
+            inner: vec![word; n.div_ceil(64)],
+        };
+        res.clear_padding();
+        res
+    }
+
+    // Cells covered by an odd number of the rectilinear polygons, which is the
+    // map minus its obstacles when given both. Scanning upwards, every
+    // horizontal edge flips the cells above it: the flips are collected per row
+    // and accumulated with a running XOR of the rows.
+    pub fn from_polygons<'a, I: IntoIterator<Item = &'a Map>>(
+        width: usize,
+        height: usize,
+        polygons: I,
+    ) -> BitMatrix {
+        let row_words = width.div_ceil(64);
+        let mut toggles = vec![0u64; row_words * height];
+        for polygon in polygons {
+            let ps = &polygon.0;
+            for (i, &p) in ps.iter().enumerate() {
+                let q = ps[(i + 1) % ps.len()];
+                let y = cmp::max(p.y, 0) as usize;
+                if p.y != q.y || y >= height {
+                    continue;
+                }
+                let start = cmp::min(cmp::max(cmp::min(p.x, q.x), 0) as usize, width);
+                let end = cmp::min(cmp::max(cmp::max(p.x, q.x), 0) as usize, width);
+                toggle_bits(&mut toggles[y * row_words..(y + 1) * row_words], start, end);
+            }
+        }
+
+        let mut res = BitMatrix::new(width, height, false);
+        let mut row = vec![0u64; row_words];
+        for (y, toggle) in toggles.chunks(cmp::max(row_words, 1)).enumerate() {
+            for (r, t) in row.iter_mut().zip(toggle) {
+                *r ^= t;
+            }
+            res.or_row(y, &row);
+        }
+        res
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, p: Point) -> Option<usize> {
+        if p.x >= 0 && p.y >= 0 && (p.x as usize) < self.width && (p.y as usize) < self.height {
+            Some(p.y as usize * self.width + p.x as usize)
+        } else {
+            None
+        }
+    }
+
+    // Sets the cells of a row given as words from its first cell. Bits past the
+    // end of the row have to be zero.
+    fn or_row(&mut self, y: usize, row: &[u64]) {
+        let base = y * self.width;
+        for (k, &word) in row.iter().enumerate() {
+            if word == 0 {
+                continue;
+            }
+            let i = base + k * 64;
+            let shift = i % 64;
+            self.inner[i / 64] |= word << shift;
+            if shift > 0 {
+                if let Some(next) = self.inner.get_mut(i / 64 + 1) {
+                    *next |= word >> (64 - shift);
+                }
+            }
+        }
+    }
+
+    // bits past the last cell stay zero so that bulk operations can ignore them
+    fn clear_padding(&mut self) {
+        let used = self.width * self.height % 64;
+        if used != 0 {
+            if let Some(last) = self.inner.last_mut() {
+                *last &= (1 << used) - 1;
+            }
+        }
+    }
+
+    pub fn get(&self, p: Point) -> Option<&bool> {
+        self.index(p).map(|i| {
+            if self.inner[i / 64] >> (i % 64) & 1 == 1 {
+                &true
+            } else {
+                &false
+            }
+        })
+    }
+
+    pub fn try_set(&mut self, p: Point, value: bool) -> Option<bool> {
+        let i = self.index(p)?;
+        let old = self.inner[i / 64] >> (i % 64) & 1 == 1;
+        if value {
+            self.inner[i / 64] |= 1 << (i % 64);
+        } else {
+            self.inner[i / 64] &= !(1 << (i % 64));
+        }
+        Some(old)
+    }
+
+    pub fn set(&mut self, p: Point, value: bool) {
+        if self.try_set(p, value).is_none() {
+            panic!("out of bound : {:?}", p);
+        }
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.inner.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn and(&mut self, other: &BitMatrix) {
+        self.zip_with(other, |a, b| a & b);
+    }
+
+    pub fn or(&mut self, other: &BitMatrix) {
+        self.zip_with(other, |a, b| a | b);
+    }
+
+    pub fn andnot(&mut self, other: &BitMatrix) {
+        self.zip_with(other, |a, b| a & !b);
+    }
+
+    fn zip_with<F: Fn(u64, u64) -> u64>(&mut self, other: &BitMatrix, f: F) {
+        assert_eq!((self.width, self.height), (other.width, other.height));
+        for (a, &b) in self.inner.iter_mut().zip(other.inner.iter()) {
+            *a = f(*a, b);
+        }
+    }
+
+    // points of the set cells in row-major order
+    pub fn iter_ones(&self) -> impl Iterator<Item = Point> + '_ {
+        let width = self.width;
+        self.inner.iter().enumerate().flat_map(move |(i, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                let idx = i * 64 + bit;
+                Some(Point::new((idx % width) as i32, (idx / width) as i32))
+            })
+        })
+    }
+}
+
+fn toggle_bits(words: &mut [u64], start: usize, end: usize) {
+    let mut i = start;
+    while i < end {
+        let bit = i % 64;
+        let len = cmp::min(64 - bit, end - i);
+        let mask = if len == 64 { !0 } else { ((1 << len) - 1) << bit };
+        words[i / 64] ^= mask;
+        i += len;
+    }
+}
+
+// cells to be wrapped: inside the map and outside every obstacle
+pub fn free_cells(task: &Task) -> BitMatrix {
+    BitMatrix::from_polygons(
+        task.width,
+        task.height,
+        std::iter::once(&task.map).chain(&task.obstacles),
+    )
+}
+
+// Manipulator visibility rule: `to` is visible from `from` if the segment
+// between the cell centers only passes through free cells. Touching the corner
+// of a blocked cell doesn't count as passing through it.
+pub fn is_visible<F: Fn(Point) -> bool>(from: Point, to: Point, is_free: F) -> bool {
+    let (x0, y0) = (2 * from.x + 1, 2 * from.y + 1);
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    // signed side of the cell corner (cx, cy) against the segment line
+    let side = |cx: i32, cy: i32| dx * (2 * cy - y0) - dy * (2 * cx - x0);
+    for x in cmp::min(from.x, to.x)..=cmp::max(from.x, to.x) {
+        for y in cmp::min(from.y, to.y)..=cmp::max(from.y, to.y) {
+            let corners = [
+                side(x, y),
+                side(x + 1, y),
+                side(x, y + 1),
+                side(x + 1, y + 1),
+            ];
+            let crossed = corners.iter().any(|&v| v < 0) && corners.iter().any(|&v| v > 0);
+            if crossed && !is_free(Point::new(x, y)) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
 pub struct Range {
     pub start: usize,
@@ -87,4 +304,166 @@ impl Range {
     pub fn remove_end(&self) -> Range {
         Range::new(self.start, self.end - 1)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::read_task;
+
+    // the cell by cell fill used before the scanline pass
+    fn fill_per_cell(task: &Task) -> BitMatrix {
+        let mut res = BitMatrix::new(task.width, task.height, false);
+        for p in task.map.enumerate_points() {
+            res.set(p, true);
+        }
+        for o in &task.obstacles {
+            for p in o.enumerate_points() {
+                res.set(p, false);
+            }
+        }
+        res
+    }
+
+    fn assert_same_fill(s: &str, expected: usize) {
+        let task = read_task(s, "test".to_owned()).unwrap();
+        let cells = free_cells(&task);
+        assert_eq!(cells, fill_per_cell(&task));
+        assert_eq!(cells.count_ones(), expected);
+    }
+
+    // sets the cells whose row-major index satisfies `f`
+    fn pattern(width: usize, height: usize, f: impl Fn(usize) -> bool) -> BitMatrix {
+        let mut res = BitMatrix::new(width, height, false);
+        for y in 0..height {
+            for x in 0..width {
+                if f(y * width + x) {
+                    res.set(Point::new(x as i32, y as i32), true);
+                }
+            }
+        }
+        res
+    }
+
+    fn cells(m: &BitMatrix) -> Vec<bool> {
+        let mut res = Vec::new();
+        for y in 0..m.height() {
+            for x in 0..m.width() {
+                res.push(*m.get(Point::new(x as i32, y as i32)).unwrap());
+            }
+        }
+        res
+    }
+
+    #[test]
+    fn bulk_operations() {
+        // 130 cells span three words, the last one padded
+        let a = pattern(13, 10, |i| i % 3 == 0);
+        let b = pattern(13, 10, |i| i % 2 == 0);
+        let (ca, cb) = (cells(&a), cells(&b));
+        let check = |m: &BitMatrix, f: &dyn Fn(bool, bool) -> bool| {
+            let expected: Vec<bool> = ca.iter().zip(&cb).map(|(&x, &y)| f(x, y)).collect();
+            assert_eq!(cells(m), expected);
+            assert_eq!(m.count_ones(), expected.iter().filter(|&&c| c).count());
+        };
+
+        let mut and = a.clone();
+        and.and(&b);
+        check(&and, &|x, y| x && y);
+        let mut or = a.clone();
+        or.or(&b);
+        check(&or, &|x, y| x || y);
+        let mut andnot = a.clone();
+        andnot.andnot(&b);
+        check(&andnot, &|x, y| x && !y);
+    }
+
+    #[test]
+    fn iter_ones_in_row_major_order() {
+        let mut m = BitMatrix::new(70, 2, false);
+        let points = [(0, 0), (63, 0), (64, 0), (69, 0), (57, 1), (58, 1), (69, 1)];
+        for &(x, y) in points.iter().rev() {
+            m.set(Point::new(x, y), true);
+        }
+        let expected: Vec<Point> = points.iter().map(|&(x, y)| Point::new(x, y)).collect();
+        assert_eq!(m.iter_ones().collect::<Vec<_>>(), expected);
+        assert_eq!(m.count_ones(), points.len());
+    }
+
+    #[test]
+    fn try_set_returns_the_old_value() {
+        let mut m = BitMatrix::new(13, 10, false);
+        // the cells around the word boundaries and the last cell
+        for &(x, y) in &[(11, 4), (12, 4), (0, 5), (11, 9), (12, 9)] {
+            let p = Point::new(x, y);
+            assert_eq!(m.try_set(p, true), Some(false));
+            assert_eq!(m.try_set(p, true), Some(true));
+            assert_eq!(m.get(p), Some(&true));
+        }
+        assert_eq!(m.count_ones(), 5);
+        assert_eq!(m.try_set(Point::new(12, 4), false), Some(true));
+        assert_eq!(m.get(Point::new(12, 4)), Some(&false));
+        for &(x, y) in &[(-1, 0), (13, 0), (0, 10), (0, -1)] {
+            assert_eq!(m.try_set(Point::new(x, y), true), None);
+        }
+        assert_eq!(m.count_ones(), 4);
+    }
+
+    #[test]
+    fn padding_stays_zero() {
+        // sizes ending on, just past and well inside a word
+        for &(width, height) in &[(8, 8), (64, 1), (65, 1), (13, 10), (3, 7), (130, 3)] {
+            let n = width * height;
+            let mut m = BitMatrix::new(width, height, true);
+            assert_eq!(m.count_ones(), n);
+            assert_eq!(m.iter_ones().count(), n);
+            assert!(m.iter_ones().all(|p| m.get(p) == Some(&true)));
+
+            let full = m.clone();
+            let empty = BitMatrix::new(width, height, false);
+            m.or(&full);
+            m.and(&full);
+            assert_eq!(m.count_ones(), n);
+            m.andnot(&empty);
+            assert_eq!(m.count_ones(), n);
+            m.andnot(&full);
+            assert_eq!(m.count_ones(), 0);
+            assert_eq!(m.iter_ones().next(), None);
+        }
+    }
+
+    #[test]
+    fn from_polygons_rectangle() {
+        assert_same_fill("(0,0),(5,0),(5,3),(0,3)#(0,0)##", 15);
+    }
+
+    #[test]
+    fn from_polygons_u_shape() {
+        assert_same_fill("(0,0),(6,0),(6,5),(4,5),(4,2),(2,2),(2,5),(0,5)#(0,0)##", 24);
+    }
+
+    #[test]
+    fn from_polygons_obstacle_on_edge() {
+        assert_same_fill(
+            "(0,0),(6,0),(6,4),(0,4)#(5,0)#(0,1),(2,1),(2,3),(0,3)#",
+            20,
+        );
+    }
+
+    #[test]
+    fn from_polygons_obstacle_hole() {
+        assert_same_fill(
+            "(0,0),(10,0),(10,10),(0,10)#(0,0)#(3,3),(6,3),(6,6),(3,6)#",
+            91,
+        );
+    }
+
+    #[test]
+    fn from_polygons_across_words() {
+        // rows and obstacle edges that do not line up with the 64-bit words
+        assert_same_fill(
+            "(0,0),(130,0),(130,3),(0,3)#(0,0)#(60,1),(70,1),(70,2),(60,2);(100,1),(129,1),(129,2),(100,2)#",
+            351,
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use crate::models::*;
+use crate::utils::{BitMatrix, Matrix};
+
+use std::cmp;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Shape {
+    Map,
+    Obstacle(usize),
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shape::Map => write!(f, "map"),
+            Shape::Obstacle(idx) => write!(f, "obstacle {}", idx),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ValidationError {
+    TooFewVertices(Shape),
+    NegativeVertex(Shape, Point),
+    NotRectilinear(Shape, Point, Point),
+    SelfIntersecting(Shape, Point),
+    RedundantVertex(Shape, Point),
+    WrongOrientation(Shape),
+    ObstacleOutside(usize, Point),
+    ObstaclesTouch(usize, usize, Point),
+    InitialBlocked(Point),
+    BoosterBlocked(Booster),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::TooFewVertices(s) => write!(f, "{} has less than 4 vertices", s),
+            ValidationError::NegativeVertex(s, p) => {
+                write!(f, "{} has a negative vertex {}", s, p)
+            }
+            ValidationError::NotRectilinear(s, p, q) => {
+                write!(f, "{} edge {}-{} is not axis-aligned", s, p, q)
+            }
+            ValidationError::SelfIntersecting(s, p) => write!(f, "{} intersects itself at {}", s, p),
+            ValidationError::RedundantVertex(s, p) => {
+                write!(f, "{} has a vertex {} on a straight edge", s, p)
+            }
+            ValidationError::WrongOrientation(s) => write!(f, "{} is not counter-clockwise", s),
+            ValidationError::ObstacleOutside(idx, p) => {
+                write!(f, "obstacle {} is not strictly inside the map at {}", idx, p)
+            }
+            ValidationError::ObstaclesTouch(a, b, p) => {
+                write!(f, "obstacles {} and {} overlap or touch at {}", a, b, p)
+            }
+            ValidationError::InitialBlocked(p) => write!(f, "initial position {} is blocked", p),
+            ValidationError::BoosterBlocked(b) => write!(f, "booster {} is blocked", b),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+// An axis-aligned edge with its ends ordered.
+#[derive(Clone, Copy)]
+struct Edge {
+    min: Point,
+    max: Point,
+}
+
+impl Edge {
+    fn new(p: Point, q: Point) -> Edge {
+        Edge {
+            min: Point::new(cmp::min(p.x, q.x), cmp::min(p.y, q.y)),
+            max: Point::new(cmp::max(p.x, q.x), cmp::max(p.y, q.y)),
+        }
+    }
+
+    fn is_vertical(&self) -> bool {
+        self.min.x == self.max.x
+    }
+
+    // some point shared by both edges
+    fn intersection(&self, other: &Edge) -> Option<Point> {
+        let min_x = cmp::max(self.min.x, other.min.x);
+        let max_x = cmp::min(self.max.x, other.max.x);
+        let min_y = cmp::max(self.min.y, other.min.y);
+        let max_y = cmp::min(self.max.y, other.max.y);
+        if min_x <= max_x && min_y <= max_y {
+            Some(Point::new(min_x, min_y))
+        } else {
+            None
+        }
+    }
+}
+
+// twice the signed area, positive for counter-clockwise polygons
+fn signed_area(polygon: &Map) -> i64 {
+    let ps = &polygon.0;
+    (0..ps.len())
+        .map(|i| {
+            let p = ps[i];
+            let q = ps[(i + 1) % ps.len()];
+            i64::from(p.x) * i64::from(q.y) - i64::from(q.x) * i64::from(p.y)
+        })
+        .sum()
+}
+
+// Checks that the polygon is a simple rectilinear one going counter-clockwise,
+// as every polygon in the contest tasks does.
+pub fn validate_polygon(polygon: &Map, shape: Shape) -> Result<(), ValidationError> {
+    let ps = &polygon.0;
+    let n = ps.len();
+    if n < 4 {
+        return Err(ValidationError::TooFewVertices(shape));
+    }
+    if let Some(&p) = ps.iter().find(|p| p.x < 0 || p.y < 0) {
+        return Err(ValidationError::NegativeVertex(shape, p));
+    }
+    let mut edges = Vec::with_capacity(n);
+    for i in 0..n {
+        let (p, q) = (ps[i], ps[(i + 1) % n]);
+        if (p.x != q.x && p.y != q.y) || p == q {
+            return Err(ValidationError::NotRectilinear(shape, p, q));
+        }
+        edges.push(Edge::new(p, q));
+    }
+
+    for i in 0..n {
+        for j in i + 1..n {
+            let adjacent = j == i + 1 || (i == 0 && j == n - 1);
+            let (a, b) = (&edges[i], &edges[j]);
+            if let Some(p) = a.intersection(b) {
+                // neighbours only meet at their common vertex, where the
+                // polygon has to turn
+                if !adjacent {
+                    return Err(ValidationError::SelfIntersecting(shape, p));
+                }
+                if a.is_vertical() == b.is_vertical() {
+                    let k = if j == i + 1 { j } else { 0 };
+                    let (prev, vertex, next) = (ps[(k + n - 1) % n], ps[k], ps[(k + 1) % n]);
+                    let dot = (vertex.x - prev.x) * (next.x - vertex.x)
+                        + (vertex.y - prev.y) * (next.y - vertex.y);
+                    // going on in the same direction or folding back
+                    return Err(if dot > 0 {
+                        ValidationError::RedundantVertex(shape, vertex)
+                    } else {
+                        ValidationError::SelfIntersecting(shape, vertex)
+                    });
+                }
+            }
+        }
+    }
+
+    if signed_area(polygon) < 0 {
+        return Err(ValidationError::WrongOrientation(shape));
+    }
+    Ok(())
+}
+
+// Checks the geometry of a task: every polygon, obstacles strictly inside the
+// map without touching each other, and the initial position and boosters on
+// cells to be wrapped.
+pub fn validate_task(task: &Task) -> Result<(), ValidationError> {
+    validate_polygon(&task.map, Shape::Map)?;
+    for (idx, o) in task.obstacles.iter().enumerate() {
+        validate_polygon(o, Shape::Obstacle(idx))?;
+    }
+
+    let width = task.map.compute_width();
+    let height = task.map.compute_height();
+    // the rasterizer clips to the map bounds, so check the vertices beyond them
+    for (idx, o) in task.obstacles.iter().enumerate() {
+        if let Some(&p) = o.0.iter().find(|p| p.x as usize > width || p.y as usize > height) {
+            return Err(ValidationError::ObstacleOutside(idx, p));
+        }
+    }
+    let inside = BitMatrix::from_polygons(width, height, std::iter::once(&task.map));
+    let obstacles = task
+        .obstacles
+        .iter()
+        .map(|o| BitMatrix::from_polygons(width, height, std::iter::once(o)))
+        .collect::<Vec<_>>();
+    // the obstacle covering each cell
+    let mut owners: Matrix<Option<usize>> = Matrix::new(width, height, None);
+    for (idx, cells) in obstacles.iter().enumerate() {
+        for p in cells.iter_ones() {
+            if let Some(Some(other)) = owners.try_set(p, Some(idx)) {
+                return Err(ValidationError::ObstaclesTouch(other, idx, p));
+            }
+            if inside.get(p) != Some(&true) {
+                return Err(ValidationError::ObstacleOutside(idx, p));
+            }
+        }
+    }
+    // polygons touch, even at a single corner, exactly when some of their
+    // cells are neighbours
+    for (idx, cells) in obstacles.iter().enumerate() {
+        for p in cells.iter_ones() {
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    let q = Point::new(p.x + dx, p.y + dy);
+                    match owners.get(q) {
+                        Some(Some(other)) if *other == idx => {}
+                        Some(Some(other)) => {
+                            return Err(ValidationError::ObstaclesTouch(idx, *other, q));
+                        }
+                        Some(None) if inside.get(q) == Some(&true) => {}
+                        _ => return Err(ValidationError::ObstacleOutside(idx, q)),
+                    }
+                }
+            }
+        }
+    }
+
+    let is_free = |p: Point| inside.get(p) == Some(&true) && owners.get(p) == Some(&None);
+    if !is_free(task.initial) {
+        return Err(ValidationError::InitialBlocked(task.initial));
+    }
+    if let Some(b) = task.boosters.iter().find(|b| !is_free(b.point)) {
+        return Err(ValidationError::BoosterBlocked(b.clone()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::read_task;
+
+    fn polygon(s: &str) -> Result<(), ValidationError> {
+        let task = read_task(&format!("{}#(0,0)##", s), "test".to_owned()).unwrap();
+        validate_polygon(&task.map, Shape::Map)
+    }
+
+    fn task(s: &str) -> Result<(), ValidationError> {
+        validate_task(&read_task(s, "test".to_owned()).unwrap())
+    }
+
+    #[test]
+    fn contest_task_is_valid() {
+        let s = include_str!("../input/prob-002.desc");
+        assert_eq!(task(s.trim_end()), Ok(()));
+    }
+
+    #[test]
+    fn polygon_errors() {
+        assert_eq!(
+            polygon("(0,0),(1,0),(1,1)"),
+            Err(ValidationError::TooFewVertices(Shape::Map))
+        );
+        assert_eq!(
+            polygon("(-1,0),(1,0),(1,1),(-1,1)"),
+            Err(ValidationError::NegativeVertex(Shape::Map, Point::new(-1, 0)))
+        );
+        assert_eq!(
+            polygon("(0,0),(2,0),(2,2),(1,3),(0,2)"),
+            Err(ValidationError::NotRectilinear(
+                Shape::Map,
+                Point::new(2, 2),
+                Point::new(1, 3)
+            ))
+        );
+        assert_eq!(
+            polygon("(0,1),(3,1),(3,2),(1,2),(1,0),(0,0)"),
+            Err(ValidationError::SelfIntersecting(Shape::Map, Point::new(1, 1)))
+        );
+        assert_eq!(
+            polygon("(0,0),(2,0),(2,2),(2,1),(0,1)"),
+            Err(ValidationError::SelfIntersecting(Shape::Map, Point::new(2, 2)))
+        );
+        assert_eq!(
+            polygon("(0,0),(0,2),(2,2),(2,0)"),
+            Err(ValidationError::WrongOrientation(Shape::Map))
+        );
+    }
+
+    #[test]
+    fn redundant_vertex() {
+        assert_eq!(
+            polygon("(0,0),(1,0),(2,0),(2,2),(0,2)"),
+            Err(ValidationError::RedundantVertex(Shape::Map, Point::new(1, 0)))
+        );
+        assert_eq!(
+            polygon("(1,0),(2,0),(2,2),(0,2),(0,0)"),
+            Err(ValidationError::RedundantVertex(Shape::Map, Point::new(1, 0)))
+        );
+    }
+
+    #[test]
+    fn obstacle_errors() {
+        assert_eq!(
+            task("(0,0),(10,0),(10,10),(0,10)#(0,0)#(2,2),(4,2),(2,4),(4,4)#"),
+            Err(ValidationError::NotRectilinear(
+                Shape::Obstacle(0),
+                Point::new(4, 2),
+                Point::new(2, 4)
+            ))
+        );
+        // touching the boundary or sticking out of the map
+        assert!(matches!(
+            task("(0,0),(10,0),(10,10),(0,10)#(5,5)#(0,2),(2,2),(2,4),(0,4)#"),
+            Err(ValidationError::ObstacleOutside(0, _))
+        ));
+        assert!(matches!(
+            task("(0,0),(10,0),(10,10),(0,10)#(5,5)#(8,2),(12,2),(12,4),(8,4)#"),
+            Err(ValidationError::ObstacleOutside(0, _))
+        ));
+        // sharing a corner or overlapping
+        assert!(matches!(
+            task("(0,0),(10,0),(10,10),(0,10)#(0,0)#(2,2),(4,2),(4,4),(2,4);(4,4),(6,4),(6,6),(4,6)#"),
+            Err(ValidationError::ObstaclesTouch(_, _, _))
+        ));
+        assert!(matches!(
+            task("(0,0),(10,0),(10,10),(0,10)#(0,0)#(2,2),(5,2),(5,5),(2,5);(3,3),(6,3),(6,6),(3,6)#"),
+            Err(ValidationError::ObstaclesTouch(_, _, _))
+        ));
+    }
+
+    #[test]
+    fn blocked_initial_and_boosters() {
+        assert_eq!(
+            task("(0,0),(10,0),(10,10),(0,10)#(3,3)#(2,2),(4,2),(4,4),(2,4)#"),
+            Err(ValidationError::InitialBlocked(Point::new(3, 3)))
+        );
+        assert_eq!(
+            task("(0,0),(6,0),(6,1),(8,1),(8,2),(6,2),(6,3),(0,3)#(0,0)##B(7,0)"),
+            Err(ValidationError::BoosterBlocked(Booster::new(
+                BoosterType::NewHand,
+                Point::new(7, 0)
+            )))
+        );
+    }
+}
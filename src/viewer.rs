@@ -0,0 +1,176 @@
+use crate::models::*;
+use crate::render::{escape_xml, robot_color, Replay, INVENTORY_KINDS};
+use crate::sim::{Robot, Simulator};
+
+use serde::Serialize;
+
+const TEMPLATE: &str = include_str!("viewer.html");
+// replaced by the timeline in the template
+const TIMELINE_MARKER: &str = "/*TIMELINE*/null";
+
+#[derive(Serialize)]
+struct Timeline<'a> {
+    id: &'a str,
+    width: usize,
+    height: usize,
+    area: usize,
+    // one string per row from y = 0, '.' for free cells and '#' for blocked ones
+    free: Vec<String>,
+    initial: (i32, i32),
+    boosters: Vec<BoosterEntry>,
+    inventory_kinds: Vec<String>,
+    colors: Vec<&'static str>,
+    frames: Vec<FrameEntry>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BoosterEntry {
+    kind: String,
+    x: i32,
+    y: i32,
+    // frame from which it is gone, and by whom
+    picked: Option<(usize, usize)>,
+}
+
+#[derive(Serialize)]
+struct FrameEntry {
+    robots: Vec<RobotEntry>,
+    painted: Vec<(i32, i32)>,
+    drilled: Vec<(i32, i32)>,
+    beacons: Vec<(i32, i32)>,
+    inventory: Vec<usize>,
+    wrapped: usize,
+}
+
+#[derive(Serialize)]
+struct RobotEntry {
+    x: i32,
+    y: i32,
+    // Direction::index
+    dir: usize,
+    fast: usize,
+    drill: usize,
+    // manipulators relative to a robot facing right, only when they changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hands: Option<Vec<(i32, i32)>>,
+}
+
+fn xy(p: Point) -> (i32, i32) {
+    (p.x, p.y)
+}
+
+fn robot_entry(robot: &Robot, prev: Option<&Robot>) -> RobotEntry {
+    let changed = prev.is_none_or(|prev| prev.hands != robot.hands);
+    RobotEntry {
+        x: robot.place.point().x,
+        y: robot.place.point().y,
+        dir: robot.place.dir().index(),
+        fast: robot.fast_turns,
+        drill: robot.drill_turns,
+        hands: if changed {
+            Some(robot.hands.iter().cloned().map(xy).collect())
+        } else {
+            None
+        },
+    }
+}
+
+// A single page that plays back a replay turn by turn, with no outside resources.
+pub fn render_html(task: &Task, replay: &Replay) -> String {
+    let initial = Simulator::new(task, &Buy::new());
+    let free = (0..task.height)
+        .map(|y| {
+            (0..task.width)
+                .map(
+                    |x| match initial.valid().get(Point::new(x as i32, y as i32)) {
+                        Some(true) => '.',
+                        _ => '#',
+                    },
+                )
+                .collect()
+        })
+        .collect();
+    let boosters = task
+        .boosters
+        .iter()
+        .map(|b| BoosterEntry {
+            kind: b.kind.to_string(),
+            x: b.point.x,
+            y: b.point.y,
+            picked: replay
+                .pickups
+                .iter()
+                .find(|p| p.point == b.point)
+                .map(|p| (p.turn, p.robot)),
+        })
+        .collect();
+    let frames = replay
+        .frames
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            let prev = if i == 0 {
+                None
+            } else {
+                Some(&replay.frames[i - 1])
+            };
+            FrameEntry {
+                robots: frame
+                    .robots
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, r)| robot_entry(r, prev.and_then(|f| f.robots.get(idx))))
+                    .collect(),
+                painted: frame.painted.iter().cloned().map(xy).collect(),
+                drilled: frame.drilled.iter().cloned().map(xy).collect(),
+                beacons: frame.beacons.iter().cloned().map(xy).collect(),
+                inventory: frame.inventory.clone(),
+                wrapped: frame.wrapped,
+            }
+        })
+        .collect();
+    let timeline = Timeline {
+        id: &task.id,
+        width: task.width,
+        height: task.height,
+        area: initial.area(),
+        free,
+        initial: xy(task.initial),
+        boosters,
+        inventory_kinds: INVENTORY_KINDS.iter().map(|k| k.to_string()).collect(),
+        colors: (0..replay.robot_count()).map(robot_color).collect(),
+        frames,
+        error: replay.error.as_ref().map(|e| e.to_string()),
+    };
+    // '<' only occurs inside JSON strings, where escaping it keeps the
+    // embedded JSON from closing the script element
+    let json = serde_json::to_string(&timeline)
+        .unwrap()
+        .replace('<', "\\u003c");
+    // split first so that neither replacement can see the other one's text
+    let marker = TEMPLATE.find(TIMELINE_MARKER).unwrap();
+    let (head, tail) = TEMPLATE.split_at(marker);
+    format!(
+        "{}{}{}",
+        head.replacen("{{title}}", &escape_xml(&task.id), 1),
+        json,
+        &tail[TIMELINE_MARKER.len()..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::{read_commands, read_task};
+
+    #[test]
+    fn id_cannot_break_out_of_the_page() {
+        let id = "</title><script>alert(1)</script>".to_owned();
+        let task = read_task("(0,0),(2,0),(2,2),(0,2)#(0,0)##", id).unwrap();
+        let replay = Replay::new(&task, &Buy::new(), &read_commands("WD").unwrap());
+        let html = render_html(&task, &replay);
+        assert!(!html.contains("<script>alert"));
+        assert!(html.contains("<title>&lt;/title&gt;&lt;script&gt;alert(1)&lt;/script&gt;</title>"));
+    }
+}